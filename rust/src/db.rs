@@ -0,0 +1,276 @@
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::convert::Infallible;
+use primitive_types::U256;
+
+use crate::*;
+
+/// Everything about an account except its code and storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasicAccount {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code_hash: B256,
+}
+
+/// A source of state for the interpreter to read from.
+pub trait Database {
+    /// Why a read failed, such as an I/O or network error. It aborts the
+    /// execution rather than halting the current frame.
+    type Error;
+
+    /// The account at `address`, or `None` if no account exists there.
+    fn basic(&mut self, address: Address) -> Result<Option<BasicAccount>, Self::Error>;
+
+    /// Code whose Keccak-256 hash is `code_hash`. Unknown hashes read as
+    /// empty code.
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytes, Self::Error>;
+
+    /// Value of storage slot `index` of `address`, zero if unset.
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error>;
+
+    /// Hash of block `number`, zero if unknown.
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error>;
+}
+
+impl<DB: Database + ?Sized> Database for &mut DB {
+    type Error = DB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<BasicAccount>, Self::Error> {
+        (**self).basic(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytes, Self::Error> {
+        (**self).code_by_hash(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        (**self).storage(address, index)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        (**self).block_hash(number)
+    }
+}
+
+// In-memory
+
+/// A database held entirely in memory.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDB {
+    accounts: State,
+    contracts: HashMap<B256, Bytes>,
+    block_hashes: HashMap<u64, B256>,
+}
+
+impl InMemoryDB {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_account(&mut self, address: Address, account: Account) {
+        self.contracts.insert(account.code_hash, account.code.clone());
+        self.accounts.insert(address, account);
+    }
+
+    pub fn insert_block_hash(&mut self, number: u64, hash: B256) {
+        self.block_hashes.insert(number, hash);
+    }
+}
+
+impl From<State> for InMemoryDB {
+    fn from(state: State) -> Self {
+        let mut db = InMemoryDB::new();
+        for (address, account) in state {
+            db.insert_account(address, account);
+        }
+        db
+    }
+}
+
+impl Database for InMemoryDB {
+    type Error = Infallible;
+
+    fn basic(&mut self, address: Address) -> Result<Option<BasicAccount>, Self::Error> {
+        Ok(self.accounts.get(&address).map(|account| BasicAccount {
+            nonce: account.nonce,
+            balance: account.balance,
+            code_hash: account.code_hash,
+        }))
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytes, Self::Error> {
+        Ok(self.contracts.get(&code_hash).cloned().unwrap_or_default())
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        Ok(self.accounts.get(&address).map(|account| account.sload(index)).unwrap_or_default())
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        Ok(self.block_hashes.get(&number).copied().unwrap_or_default())
+    }
+}
+
+// Cache
+
+/// Caches what is read from `db` and holds the changes made on top of it.
+/// The underlying database is never written to.
+#[derive(Debug, Clone, Default)]
+pub struct CacheDB<DB> {
+    /// Loaded accounts, `None` for addresses known to hold no account.
+    accounts: HashMap<Address, Option<Account>>,
+    /// Addresses whose storage in `db` is hidden, because their account was
+    /// replaced or removed. Slots missing from the cache read as zero.
+    storage_cleared: HashSet<Address>,
+    block_hashes: HashMap<u64, B256>,
+    pub db: DB,
+}
+
+impl<DB: Database> CacheDB<DB> {
+    pub fn new(db: DB) -> Self {
+        Self {
+            accounts: HashMap::new(),
+            storage_cleared: HashSet::new(),
+            block_hashes: HashMap::new(),
+            db,
+        }
+    }
+
+    /// Puts `account` at `address`, replacing whatever the database holds
+    /// there. Storage slots missing from `account` read as zero.
+    pub fn insert_account(&mut self, address: Address, account: Account) {
+        self.accounts.insert(address, Some(account));
+        self.storage_cleared.insert(address);
+    }
+
+    /// Removes the account at `address`, hiding whatever the database holds
+    /// there, storage included. An account created there later starts with
+    /// empty storage.
+    pub fn remove_account(&mut self, address: Address) {
+        self.accounts.insert(address, None);
+        self.storage_cleared.insert(address);
+    }
+
+    /// The account at `address`, loading it on first access.
+    pub fn account(&mut self, address: Address) -> Result<Option<&Account>, DB::Error> {
+        Ok(self.load(address)?.as_ref())
+    }
+
+    /// The account at `address` for writing, created empty if it does not
+    /// exist.
+    pub fn account_mut(&mut self, address: Address) -> Result<&mut Account, DB::Error> {
+        Ok(self.load(address)?.get_or_insert_with(Account::new))
+    }
+
+    /// Like `account_mut`, for an address that is already loaded, so that
+    /// the database is not read.
+    pub(crate) fn loaded_account_mut(&mut self, address: Address) -> &mut Account {
+        self.accounts.entry(address).or_default().get_or_insert_with(Account::new)
+    }
+
+    /// Value of storage slot `index` of `address`, loading it on first
+    /// access.
+    pub fn sload(&mut self, address: Address, index: U256) -> Result<U256, DB::Error> {
+        if let Some(slot) = self.account(address)?.and_then(|account| account.storage.get(&index)) {
+            return Ok(slot.value);
+        }
+        let value = if self.storage_cleared.contains(&address) {
+            U256::zero()
+        } else {
+            self.db.storage(address, index)?
+        };
+        if let Some(account) = self.load(address)? {
+            account.storage.insert(index, StorageSlot::new(value));
+        }
+        Ok(value)
+    }
+
+    /// Sets storage slot `index` of `address`, loading its original value
+    /// first so that it is kept.
+    pub fn sstore(&mut self, address: Address, index: U256, value: U256) -> Result<(), DB::Error> {
+        let present = self.sload(address, index)?;
+        self.account_mut(address)?.storage
+            .entry(index)
+            .or_insert_with(|| StorageSlot::new(present))
+            .value = value;
+        Ok(())
+    }
+
+    /// Value of storage slot `index` of `address` at the start of the
+    /// transaction.
+    pub fn original_value(&mut self, address: Address, index: U256) -> Result<U256, DB::Error> {
+        let present = self.sload(address, index)?;
+        Ok(self.account(address)?
+            .and_then(|account| account.storage.get(&index))
+            .map_or(present, |slot| slot.original_value))
+    }
+
+    /// Makes the present value of every slot its original value, as at the
+    /// start of a new transaction.
+    pub fn reset_original_values(&mut self) {
+        for account in self.accounts.values_mut().flatten() {
+            for slot in account.storage.values_mut() {
+                slot.original_value = slot.value;
+            }
+        }
+    }
+
+    fn load(&mut self, address: Address) -> Result<&mut Option<Account>, DB::Error> {
+        let Self { accounts, db, .. } = self;
+        match accounts.entry(address) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let account = match db.basic(address)? {
+                    Some(info) => {
+                        let mut account = Account::new();
+                        account.nonce = info.nonce;
+                        account.balance = info.balance;
+                        if info.code_hash != KECCAK_EMPTY {
+                            account.code = db.code_by_hash(info.code_hash)?;
+                            account.code_hash = info.code_hash;
+                        }
+                        Some(account)
+                    },
+                    None => None,
+                };
+                Ok(entry.insert(account))
+            },
+        }
+    }
+}
+
+impl<DB: Database> Database for CacheDB<DB> {
+    type Error = DB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<BasicAccount>, Self::Error> {
+        Ok(self.account(address)?.map(|account| BasicAccount {
+            nonce: account.nonce,
+            balance: account.balance,
+            code_hash: account.code_hash,
+        }))
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytes, Self::Error> {
+        let cached = self.accounts.values()
+            .flatten()
+            .find(|account| account.code_hash == code_hash)
+            .map(|account| account.code.clone());
+        match cached {
+            Some(code) => Ok(code),
+            None => self.db.code_by_hash(code_hash),
+        }
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.sload(address, index)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        if let Some(hash) = self.block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+        let hash = self.db.block_hash(number)?;
+        self.block_hashes.insert(number, hash);
+        Ok(hash)
+    }
+}
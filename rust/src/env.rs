@@ -0,0 +1,281 @@
+use primitive_types::U256;
+use serde::Deserialize;
+
+use crate::*;
+
+/// Everything an execution reads besides code and state: the rules to
+/// apply, the block it runs in and the transaction that started it.
+#[derive(Debug, Clone, Default)]
+pub struct Env {
+    pub cfg: CfgEnv,
+    pub block: BlockEnv,
+    pub tx: TxEnv,
+}
+
+impl Env {
+    pub fn new(cfg: CfgEnv, block: BlockEnv, tx: TxEnv) -> Self {
+        Env { cfg, block, tx }
+    }
+}
+
+// Cfg
+
+/// Settings that decide which rules an interpreter applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CfgEnv {
+    /// Returned by CHAINID.
+    pub chain_id: u64,
+    /// Hardfork whose opcodes, gas costs and precompiles are used.
+    pub spec: SpecId,
+    /// Largest deployable contract (EIP-170). Initcode may be twice as
+    /// large (EIP-3860).
+    pub limit_contract_code_size: usize,
+    /// When set, gas is still counted but never runs out: the root frame gets
+    /// `u64::MAX` and sub-calls ignore the gas they ask for.
+    pub unlimited_gas: bool,
+    /// When set, value transfers do not require the sender to hold the value.
+    pub disable_balance_check: bool,
+}
+
+impl CfgEnv {
+    pub fn new(spec: SpecId) -> Self {
+        CfgEnv {
+            spec,
+            ..CfgEnv::default()
+        }
+    }
+
+    pub fn builder() -> CfgEnvBuilder {
+        CfgEnvBuilder(CfgEnv::default())
+    }
+
+    /// Largest initcode accepted by CREATE and CREATE2 (EIP-3860).
+    pub fn limit_initcode_size(&self) -> usize {
+        2 * self.limit_contract_code_size
+    }
+}
+
+impl Default for CfgEnv {
+    fn default() -> Self {
+        CfgEnv {
+            chain_id: 1,
+            spec: SpecId::default(),
+            limit_contract_code_size: MAX_CODE_SIZE,
+            unlimited_gas: false,
+            disable_balance_check: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CfgEnvBuilder(CfgEnv);
+
+impl CfgEnvBuilder {
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.0.chain_id = chain_id;
+        self
+    }
+
+    pub fn spec(mut self, spec: SpecId) -> Self {
+        self.0.spec = spec;
+        self
+    }
+
+    pub fn limit_contract_code_size(mut self, size: usize) -> Self {
+        self.0.limit_contract_code_size = size;
+        self
+    }
+
+    /// Turns gas limits off, for running code written without gas in mind
+    /// such as the `evm.json` fixtures.
+    pub fn unlimited_gas(mut self, unlimited_gas: bool) -> Self {
+        self.0.unlimited_gas = unlimited_gas;
+        self
+    }
+
+    /// Lets value transfers go through whatever the sender's balance.
+    pub fn disable_balance_check(mut self, disable_balance_check: bool) -> Self {
+        self.0.disable_balance_check = disable_balance_check;
+        self
+    }
+
+    pub fn build(self) -> CfgEnv {
+        self.0
+    }
+}
+
+// Block
+
+/// The block a transaction executes in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockEnv {
+    pub number: u64,
+    pub coinbase: Address,
+    pub timestamp: u64,
+    pub gas_limit: u64,
+    pub basefee: U256,
+    pub difficulty: U256,
+    /// Beacon chain randomness, returned by PREVRANDAO since the Merge in
+    /// place of the difficulty.
+    pub prevrandao: Option<B256>,
+    pub excess_blob_gas: u64,
+}
+
+impl BlockEnv {
+    pub fn builder() -> BlockEnvBuilder {
+        BlockEnvBuilder(BlockEnv::default())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockEnvBuilder(BlockEnv);
+
+impl BlockEnvBuilder {
+    pub fn number(mut self, number: u64) -> Self {
+        self.0.number = number;
+        self
+    }
+
+    pub fn coinbase(mut self, coinbase: Address) -> Self {
+        self.0.coinbase = coinbase;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.0.timestamp = timestamp;
+        self
+    }
+
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.0.gas_limit = gas_limit;
+        self
+    }
+
+    pub fn basefee(mut self, basefee: U256) -> Self {
+        self.0.basefee = basefee;
+        self
+    }
+
+    pub fn difficulty(mut self, difficulty: U256) -> Self {
+        self.0.difficulty = difficulty;
+        self
+    }
+
+    pub fn prevrandao(mut self, prevrandao: B256) -> Self {
+        self.0.prevrandao = Some(prevrandao);
+        self
+    }
+
+    pub fn excess_blob_gas(mut self, excess_blob_gas: u64) -> Self {
+        self.0.excess_blob_gas = excess_blob_gas;
+        self
+    }
+
+    pub fn build(self) -> BlockEnv {
+        self.0
+    }
+}
+
+// Tx
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AccessListItem {
+    pub address: Address,
+    #[serde(rename = "storageKeys", default)]
+    pub storage_keys: Vec<B256>,
+}
+
+/// The transaction being executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxEnv {
+    pub caller: Address,
+    /// Returned by ORIGIN, the caller if unset.
+    pub origin: Option<Address>,
+    /// Account whose code runs.
+    pub to: Address,
+    /// Moved from `caller` to `to` before the code runs.
+    pub value: U256,
+    pub data: Bytes,
+    pub gas_limit: u64,
+    pub gas_price: U256,
+    /// Addresses and storage slots to warm before execution (EIP-2930).
+    pub access_list: Vec<AccessListItem>,
+    /// Versioned hashes of the blobs carried by the transaction (EIP-4844).
+    pub blob_hashes: Vec<B256>,
+}
+
+impl TxEnv {
+    pub fn builder() -> TxEnvBuilder {
+        TxEnvBuilder(TxEnv::default())
+    }
+}
+
+impl Default for TxEnv {
+    fn default() -> Self {
+        TxEnv {
+            caller: Address::ZERO,
+            origin: None,
+            to: Address::ZERO,
+            value: U256::zero(),
+            data: Bytes::default(),
+            gas_limit: DEFAULT_GAS_LIMIT,
+            gas_price: U256::zero(),
+            access_list: Vec::new(),
+            blob_hashes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TxEnvBuilder(TxEnv);
+
+impl TxEnvBuilder {
+    pub fn caller(mut self, caller: Address) -> Self {
+        self.0.caller = caller;
+        self
+    }
+
+    pub fn origin(mut self, origin: Address) -> Self {
+        self.0.origin = Some(origin);
+        self
+    }
+
+    pub fn to(mut self, to: Address) -> Self {
+        self.0.to = to;
+        self
+    }
+
+    pub fn value(mut self, value: U256) -> Self {
+        self.0.value = value;
+        self
+    }
+
+    pub fn data(mut self, data: impl Into<Bytes>) -> Self {
+        self.0.data = data.into();
+        self
+    }
+
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.0.gas_limit = gas_limit;
+        self
+    }
+
+    pub fn gas_price(mut self, gas_price: U256) -> Self {
+        self.0.gas_price = gas_price;
+        self
+    }
+
+    pub fn access_list(mut self, access_list: Vec<AccessListItem>) -> Self {
+        self.0.access_list = access_list;
+        self
+    }
+
+    pub fn blob_hashes(mut self, blob_hashes: Vec<B256>) -> Self {
+        self.0.blob_hashes = blob_hashes;
+        self
+    }
+
+    pub fn build(self) -> TxEnv {
+        self.0
+    }
+}
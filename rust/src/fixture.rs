@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use primitive_types::U256;
+use serde::{de::{self, DeserializeOwned}, Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+use crate::*;
+
+#[derive(Debug, Clone, Default)]
+pub struct Tx {
+    pub from: Option<Address>,
+    pub to: Option<Address>,
+    pub origin: Option<Address>,
+    pub gasprice: Option<U256>,
+    pub value: Option<U256>,
+    pub data: Option<Bytes>,
+    pub gas: Option<u64>,
+    /// Addresses and storage slots to warm before execution (EIP-2930).
+    pub access_list: Option<Vec<AccessListItem>>,
+    /// Versioned hashes of the blobs carried by the transaction (EIP-4844).
+    pub blob_versioned_hashes: Option<Vec<B256>>,
+}
+
+impl<'de> Deserialize<'de> for Tx {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut fields = Fields::deserialize(deserializer, "tx")?;
+        Ok(Tx {
+            from: fields.take("from")?,
+            to: fields.take("to")?,
+            origin: fields.take("origin")?,
+            gasprice: fields.take::<Quantity<U256>, _>("gasprice")?.map(|q| q.0),
+            value: fields.take::<Quantity<U256>, _>("value")?.map(|q| q.0),
+            data: fields.take::<HexBytes, _>("data")?.map(|bytes| bytes.0),
+            gas: fields.take::<Quantity<u64>, _>("gas")?.map(|q| q.0),
+            access_list: fields.take("accessList")?,
+            blob_versioned_hashes: fields.take("blobVersionedHashes")?,
+        })
+    }
+}
+
+impl From<&Tx> for TxEnv {
+    fn from(tx: &Tx) -> Self {
+        TxEnv {
+            caller: tx.from.unwrap_or_default(),
+            origin: tx.origin,
+            to: tx.to.unwrap_or_default(),
+            value: tx.value.unwrap_or_default(),
+            data: tx.data.clone().unwrap_or_default(),
+            gas_limit: tx.gas.unwrap_or(DEFAULT_GAS_LIMIT),
+            gas_price: tx.gasprice.unwrap_or_default(),
+            access_list: tx.access_list.clone().unwrap_or_default(),
+            blob_hashes: tx.blob_versioned_hashes.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Block {
+    pub coinbase: Option<Address>,
+    pub timestamp: Option<u64>,
+    pub number: Option<u64>,
+    pub difficulty: Option<U256>,
+    pub gaslimit: Option<u64>,
+    pub chainid: Option<u64>,
+    pub basefee: Option<U256>,
+    /// Beacon chain randomness, returned by PREVRANDAO since the Merge.
+    pub prevrandao: Option<B256>,
+    pub excess_blob_gas: Option<u64>,
+}
+
+impl<'de> Deserialize<'de> for Block {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut fields = Fields::deserialize(deserializer, "block")?;
+        Ok(Block {
+            coinbase: fields.take("coinbase")?,
+            timestamp: fields.take::<Quantity<u64>, _>("timestamp")?.map(|q| q.0),
+            number: fields.take::<Quantity<u64>, _>("number")?.map(|q| q.0),
+            difficulty: fields.take::<Quantity<U256>, _>("difficulty")?.map(|q| q.0),
+            gaslimit: fields.take::<Quantity<u64>, _>("gaslimit")?.map(|q| q.0),
+            chainid: fields.take::<Quantity<u64>, _>("chainid")?.map(|q| q.0),
+            basefee: fields.take::<Quantity<U256>, _>("basefee")?.map(|q| q.0),
+            prevrandao: fields.take("prevrandao")?,
+            excess_blob_gas: fields.take::<Quantity<u64>, _>("excessBlobGas")?.map(|q| q.0),
+        })
+    }
+}
+
+/// The block described by the fixture. Its chain id belongs in
+/// `CfgEnv::chain_id`.
+impl From<&Block> for BlockEnv {
+    fn from(block: &Block) -> Self {
+        BlockEnv {
+            number: block.number.unwrap_or_default(),
+            coinbase: block.coinbase.unwrap_or_default(),
+            timestamp: block.timestamp.unwrap_or_default(),
+            gas_limit: block.gaslimit.unwrap_or_default(),
+            basefee: block.basefee.unwrap_or_default(),
+            difficulty: block.difficulty.unwrap_or_default(),
+            prevrandao: block.prevrandao,
+            excess_blob_gas: block.excess_blob_gas.unwrap_or_default(),
+        }
+    }
+}
+
+/// Accounts to set up before execution, keyed by address. Keys may be
+/// shortened addresses such as `0x1000`.
+#[derive(Debug, Clone, Default)]
+pub struct StateInfo(pub HashMap<Address, AccountInfo>);
+
+impl StateInfo {
+    /// Builds the typed state described by the fixture.
+    pub fn to_state(&self) -> State {
+        self.0.iter()
+            .map(|(address, info)| {
+                let mut account = Account::new();
+                account.balance = info.balance.unwrap_or_default();
+                if let Some(code) = info.code.as_ref().and_then(|code| code.bin.clone()) {
+                    account.set_code(code);
+                }
+                (*address, account)
+            })
+            .collect()
+    }
+}
+
+impl<'de> Deserialize<'de> for StateInfo {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let accounts = Map::<String, Value>::deserialize(deserializer)?;
+        accounts.into_iter()
+            .map(|(key, value)| {
+                let address = Address::deserialize(Value::String(key.clone()))
+                    .map_err(|err| de::Error::custom(format_args!("state.{}: {}", key, err)))?;
+                let mut fields = Fields::new(value, format!("state.{}", key))?;
+                let code = match fields.take::<Value, _>("code")? {
+                    Some(code) => {
+                        let mut code = Fields::new(code, format!("state.{}.code", key))?;
+                        Some(AccountCode { bin: code.take::<HexBytes, _>("bin")?.map(|bytes| bytes.0) })
+                    },
+                    None => None,
+                };
+                let info = AccountInfo {
+                    balance: fields.take::<Quantity<U256>, _>("balance")?.map(|q| q.0),
+                    code,
+                };
+                Ok((address, info))
+            })
+            .collect::<Result<_, D::Error>>()
+            .map(StateInfo)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AccountInfo {
+    pub balance: Option<U256>,
+    pub code: Option<AccountCode>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AccountCode {
+    pub bin: Option<Bytes>,
+}
+
+/// The fields of a fixture object, read one at a time so that errors can
+/// name the field they come from. Unknown fields are ignored.
+struct Fields {
+    /// Path of the object, such as `tx` or `state.0x1000`.
+    path: String,
+    values: Map<String, Value>,
+}
+
+impl Fields {
+    fn deserialize<'de, D: Deserializer<'de>>(deserializer: D, path: &str) -> Result<Self, D::Error> {
+        let values = Map::deserialize(deserializer)
+            .map_err(|err| de::Error::custom(format_args!("{}: {}", path, err)))?;
+        Ok(Fields { path: path.to_string(), values })
+    }
+
+    fn new<E: de::Error>(value: Value, path: String) -> Result<Self, E> {
+        match value {
+            Value::Object(values) => Ok(Fields { path, values }),
+            other => Err(E::custom(format_args!("{}: expected an object, found {}", path, other))),
+        }
+    }
+
+    /// The value of field `key`, `None` if it is missing or null.
+    fn take<T: DeserializeOwned, E: de::Error>(&mut self, key: &str) -> Result<Option<T>, E> {
+        match self.values.remove(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => T::deserialize(value)
+                .map(Some)
+                .map_err(|err| E::custom(format_args!("{}.{}: {}", self.path, key, err))),
+        }
+    }
+}
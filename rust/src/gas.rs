@@ -0,0 +1,354 @@
+use primitive_types::U256;
+
+use crate::*;
+
+/// Gas limit used when a transaction does not specify one.
+pub const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+
+pub const ZERO: u64 = 0;
+pub const JUMPDEST_GAS: u64 = 1;
+pub const BASE: u64 = 2;
+pub const VERYLOW: u64 = 3;
+pub const LOW: u64 = 5;
+pub const MID: u64 = 8;
+pub const HIGH: u64 = 10;
+pub const EXP_GAS: u64 = 10;
+pub const KECCAK256_GAS: u64 = 30;
+pub const KECCAK256_WORD: u64 = 6;
+pub const COPY_WORD: u64 = 3;
+pub const BLOCKHASH_GAS: u64 = 20;
+pub const LOG_GAS: u64 = 375;
+pub const LOG_TOPIC: u64 = 375;
+pub const LOG_DATA: u64 = 8;
+pub const CREATE_GAS: u64 = 32_000;
+pub const MEMORY_WORD: u64 = 3;
+pub const QUADRATIC_DENOMINATOR: u64 = 512;
+pub const WARM_STORAGE_READ: u64 = 100;
+pub const COLD_ACCOUNT_ACCESS: u64 = 2_600;
+pub const CALL_VALUE: u64 = 9_000;
+pub const CALL_STIPEND: u64 = 2_300;
+pub const NEW_ACCOUNT: u64 = 25_000;
+pub const CODE_DEPOSIT: u64 = 200;
+pub const MIN_BLOB_BASE_FEE: u64 = 1;
+pub const TX_BASE: u64 = 21_000;
+pub const TX_DATA_ZERO: u64 = 4;
+pub const ACCESS_LIST_ADDRESS: u64 = 2_400;
+pub const ACCESS_LIST_STORAGE_KEY: u64 = 1_900;
+pub const IDENTITY_GAS: u64 = 15;
+pub const IDENTITY_WORD: u64 = 3;
+
+/// Costs that changed between hardforks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasSchedule {
+    pub sload: u64,
+    pub balance: u64,
+    pub extcode: u64,
+    pub extcodehash: u64,
+    pub call: u64,
+    pub selfdestruct: u64,
+    pub exp_byte: u64,
+    pub sstore_set: u64,
+    pub sstore_reset: u64,
+    pub sstore_clears_refund: u64,
+    /// Intrinsic cost of a non-zero byte of transaction data, lowered by
+    /// EIP-2028 in Istanbul.
+    pub tx_data_nonzero: u64,
+    /// Refund for destroying a contract. Zero since London (EIP-3529).
+    pub selfdestruct_refund: u64,
+    /// Whether SSTORE is metered by the net gas rules of EIP-2200, which
+    /// compare against the value at the start of the transaction.
+    pub net_sstore: bool,
+    /// Refunds pay back at most `gas_used / max_refund_quotient`.
+    pub max_refund_quotient: u64,
+    /// Extra charge on top of the warm cost for the first access to an
+    /// account in a transaction (EIP-2929). Zero before Berlin.
+    pub cold_account_access: u64,
+    /// Extra charge on top of the warm cost for the first access to a
+    /// storage slot in a transaction (EIP-2929). Zero before Berlin.
+    pub cold_sload: u64,
+    /// Extra charge for an SSTORE to a slot not yet accessed in the
+    /// transaction (EIP-2929). Zero before Berlin.
+    pub cold_sstore: u64,
+    /// Per-word charge for initcode (EIP-3860). Zero before Shanghai.
+    pub initcode_word: u64,
+    /// How fast the blob base fee follows excess blob gas (EIP-4844,
+    /// raised by EIP-7691 in Prague).
+    pub blob_base_fee_update_fraction: u64,
+}
+
+impl GasSchedule {
+    pub fn new(spec: SpecId) -> Self {
+        let mut schedule = GasSchedule {
+            sload: 50,
+            balance: 20,
+            extcode: 20,
+            extcodehash: 0,
+            call: 40,
+            selfdestruct: 0,
+            exp_byte: 10,
+            sstore_set: 20_000,
+            sstore_reset: 5_000,
+            sstore_clears_refund: 15_000,
+            tx_data_nonzero: 68,
+            selfdestruct_refund: 24_000,
+            net_sstore: false,
+            max_refund_quotient: 2,
+            cold_account_access: 0,
+            cold_sload: 0,
+            cold_sstore: 0,
+            initcode_word: 0,
+            blob_base_fee_update_fraction: 3_338_477,
+        };
+        if spec.is_enabled_in(SpecId::TangerineWhistle) {
+            schedule.sload = 200;
+            schedule.balance = 400;
+            schedule.extcode = 700;
+            schedule.call = 700;
+            schedule.selfdestruct = 5_000;
+        }
+        if spec.is_enabled_in(SpecId::SpuriousDragon) {
+            schedule.exp_byte = 50;
+        }
+        if spec.is_enabled_in(SpecId::Constantinople) {
+            schedule.extcodehash = 400;
+        }
+        if spec.is_enabled_in(SpecId::Istanbul) {
+            schedule.sload = 800;
+            schedule.balance = 700;
+            schedule.extcodehash = 700;
+            schedule.tx_data_nonzero = 16;
+            schedule.net_sstore = true;
+        }
+        if spec.is_enabled_in(SpecId::Berlin) {
+            schedule.sload = WARM_STORAGE_READ;
+            schedule.balance = WARM_STORAGE_READ;
+            schedule.extcode = WARM_STORAGE_READ;
+            schedule.extcodehash = WARM_STORAGE_READ;
+            schedule.call = WARM_STORAGE_READ;
+            schedule.sstore_reset = 5_000 - 2_100;
+            schedule.cold_account_access = COLD_ACCOUNT_ACCESS - WARM_STORAGE_READ;
+            schedule.cold_sload = 2_100 - WARM_STORAGE_READ;
+            schedule.cold_sstore = 2_100;
+        }
+        if spec.is_enabled_in(SpecId::London) {
+            schedule.sstore_clears_refund = 4_800;
+            schedule.selfdestruct_refund = 0;
+            schedule.max_refund_quotient = 5;
+        }
+        if spec.is_enabled_in(SpecId::Shanghai) {
+            schedule.initcode_word = 2;
+        }
+        if spec.is_enabled_in(SpecId::Prague) {
+            schedule.blob_base_fee_update_fraction = 5_007_716;
+        }
+        schedule
+    }
+
+    /// The fixed part of an opcode's cost, charged before it executes.
+    pub fn static_gas(&self, opcode: u8) -> u64 {
+        match opcode {
+            STOP | RETURN | REVERT | INVALID => ZERO,
+            JUMPDEST => JUMPDEST_GAS,
+            ADDRESS | ORIGIN | CALLER | CALLVALUE | CALLDATASIZE | CODESIZE | GASPRICE | COINBASE
+            | TIMESTAMP | NUMBER | DIFFICULTY | GASLIMIT | CHAINID | RETURNDATASIZE | POP | PC
+            | MSIZE | GAS | BASEFEE | BLOBBASEFEE | PUSH0 => BASE,
+            ADD | SUB | NOT | LT | GT | SLT | SGT | EQ | ISZERO | AND | OR | XOR | BYTE | SHL
+            | SHR | SAR | CALLDATALOAD | MLOAD | MSTORE | MSTORE8 | CALLDATACOPY | CODECOPY
+            | RETURNDATACOPY | MCOPY | BLOBHASH => VERYLOW,
+            PUSH1..=PUSH32 | DUP1..=DUP16 | SWAP1..=SWAP16 => VERYLOW,
+            MUL | DIV | SDIV | MOD | SMOD | SIGNEXTEND | SELFBALANCE => LOW,
+            ADDMOD | MULMOD | JUMP => MID,
+            JUMPI => HIGH,
+            EXP => EXP_GAS,
+            KECCAK256 => KECCAK256_GAS,
+            BLOCKHASH => BLOCKHASH_GAS,
+            SLOAD => self.sload,
+            BALANCE => self.balance,
+            EXTCODESIZE | EXTCODECOPY => self.extcode,
+            EXTCODEHASH => self.extcodehash,
+            CALL | CALLCODE | DELEGATECALL | STATICCALL => self.call,
+            SELFDESTRUCT => self.selfdestruct,
+            TLOAD | TSTORE => WARM_STORAGE_READ,
+            LOG0..=LOG4 => LOG_GAS + LOG_TOPIC * (opcode - LOG0) as u64,
+            CREATE | CREATE2 => CREATE_GAS,
+            _ => ZERO,
+        }
+    }
+
+    /// Gas a transaction pays before its first instruction: the base cost,
+    /// its data and, since Berlin, its access list (EIP-2930).
+    pub fn intrinsic_gas(&self, spec: SpecId, data: &[u8], access_list: &[AccessListItem]) -> u64 {
+        let zeros = data.iter().filter(|&&byte| byte == 0).count() as u64;
+        let nonzeros = data.len() as u64 - zeros;
+        let mut gas = TX_BASE + TX_DATA_ZERO * zeros + self.tx_data_nonzero * nonzeros;
+        if spec.is_enabled_in(SpecId::Berlin) {
+            for item in access_list {
+                gas += ACCESS_LIST_ADDRESS + ACCESS_LIST_STORAGE_KEY * item.storage_keys.len() as u64;
+            }
+        }
+        gas
+    }
+
+    pub fn exp_cost(&self, exponent: U256) -> u64 {
+        self.exp_byte * exponent.bits().div_ceil(8) as u64
+    }
+
+    /// Cost of an SSTORE of `new` into a slot holding `current`, which held
+    /// `original` at the start of the transaction. Does not include the
+    /// cold surcharge.
+    pub fn sstore_cost(&self, original: U256, current: U256, new: U256) -> u64 {
+        if !self.net_sstore {
+            return if current.is_zero() && !new.is_zero() { self.sstore_set } else { self.sstore_reset };
+        }
+        if current == new || original != current {
+            self.sload
+        } else if original.is_zero() {
+            self.sstore_set
+        } else {
+            self.sstore_reset
+        }
+    }
+
+    /// Change to the refund counter caused by the same SSTORE
+    /// (EIP-2200, with the amounts of EIP-3529 since London).
+    pub fn sstore_refund(&self, original: U256, current: U256, new: U256) -> i64 {
+        let clears_refund = self.sstore_clears_refund as i64;
+        if !self.net_sstore {
+            return if !current.is_zero() && new.is_zero() { clears_refund } else { 0 };
+        }
+        if current == new {
+            return 0;
+        }
+        if original == current {
+            return if !original.is_zero() && new.is_zero() { clears_refund } else { 0 };
+        }
+
+        let mut refund = 0;
+        if !original.is_zero() {
+            if current.is_zero() {
+                refund -= clears_refund;
+            } else if new.is_zero() {
+                refund += clears_refund;
+            }
+        }
+        if original == new {
+            let restored = if original.is_zero() { self.sstore_set } else { self.sstore_reset };
+            refund += (restored - self.sload) as i64;
+        }
+        refund
+    }
+
+    /// Price of one unit of blob gas in a block with `excess_blob_gas`
+    /// (EIP-4844).
+    pub fn blob_base_fee(&self, excess_blob_gas: U256) -> U256 {
+        fake_exponential(U256::from(MIN_BLOB_BASE_FEE), excess_blob_gas, U256::from(self.blob_base_fee_update_fraction))
+    }
+
+    /// Largest part of `gas_used` that refunds may pay back: a fifth since
+    /// London (EIP-3529), half before.
+    pub fn max_refund(&self, gas_used: u64) -> u64 {
+        gas_used / self.max_refund_quotient
+    }
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        Self::new(SpecId::default())
+    }
+}
+
+/// Approximates `factor * e^(numerator / denominator)` with integer
+/// arithmetic, as specified by EIP-4844. Saturates instead of overflowing.
+pub fn fake_exponential(factor: U256, numerator: U256, denominator: U256) -> U256 {
+    let mut output = U256::zero();
+    let mut accumulator = factor.saturating_mul(denominator);
+    let mut i = U256::one();
+    while !accumulator.is_zero() {
+        output = output.saturating_add(accumulator);
+        let Some(product) = accumulator.checked_mul(numerator) else {
+            return U256::MAX;
+        };
+        accumulator = product / denominator.saturating_mul(i);
+        i += U256::one();
+    }
+    output / denominator
+}
+
+/// Number of 32-byte words needed to hold `size` bytes.
+pub fn words(size: usize) -> u64 {
+    size.div_ceil(32) as u64
+}
+
+/// Total cost of a memory of `words` words.
+pub fn memory_cost(words: u64) -> u64 {
+    MEMORY_WORD * words + words * words / QUADRATIC_DENOMINATOR
+}
+
+/// Per-word cost of copying `size` bytes (CALLDATACOPY, CODECOPY, ...).
+pub fn copy_cost(size: usize) -> u64 {
+    COPY_WORD * words(size)
+}
+
+pub fn keccak256_cost(size: usize) -> u64 {
+    KECCAK256_WORD * words(size)
+}
+
+pub fn log_data_cost(size: usize) -> u64 {
+    LOG_DATA * size as u64
+}
+
+/// Gas accounting for a single frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Gas {
+    limit: u64,
+    used: u64,
+    /// Refund counter. It can go negative within a frame when a later
+    /// SSTORE takes back an earlier refund.
+    refunded: i64,
+}
+
+impl Gas {
+    pub fn new(limit: u64) -> Self {
+        Self { limit, used: 0, refunded: 0 }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn spent(&self) -> u64 {
+        self.used
+    }
+
+    pub fn refunded(&self) -> i64 {
+        self.refunded
+    }
+
+    pub fn record_refund(&mut self, refund: i64) {
+        self.refunded += refund;
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.limit - self.used
+    }
+
+    /// Gives back gas that was charged but not used, such as what a
+    /// sub-call leaves over.
+    pub fn reclaim(&mut self, amount: u64) {
+        self.used = self.used.saturating_sub(amount);
+    }
+
+    pub fn record_cost(&mut self, cost: u64) -> Result<(), EvmError> {
+        if cost > self.remaining() {
+            self.used = self.limit;
+            return Err(EvmError::OutOfGas);
+        }
+        self.used += cost;
+        Ok(())
+    }
+
+    /// Consumes everything that is left, as an exceptional halt does.
+    pub fn spend_all(&mut self) {
+        self.used = self.limit;
+    }
+}
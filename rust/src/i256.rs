@@ -0,0 +1,209 @@
+use std::{cmp::Ordering, ops::{Div, Rem}};
+use primitive_types::U256;
+
+/// A signed 256-bit integer stored as its two's complement `U256` word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct I256(pub U256);
+
+impl I256 {
+    /// -2^255, the only value whose negation does not fit.
+    pub const MIN: I256 = I256(U256([0, 0, 0, 0x8000_0000_0000_0000]));
+    pub const MINUS_ONE: I256 = I256(U256::MAX);
+
+    pub fn is_negative(&self) -> bool {
+        self.0.bit(255)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// Absolute value as an unsigned word. `MIN` maps to 2^255.
+    pub fn unsigned_abs(&self) -> U256 {
+        if self.is_negative() {
+            two_complement(self.0)
+        } else {
+            self.0
+        }
+    }
+
+    pub fn wrapping_neg(self) -> I256 {
+        I256(two_complement(self.0))
+    }
+
+    fn from_magnitude(magnitude: U256, negative: bool) -> I256 {
+        if negative {
+            I256(two_complement(magnitude))
+        } else {
+            I256(magnitude)
+        }
+    }
+
+    /// Arithmetic right shift. Shifts of 256 or more leave only the sign.
+    pub fn sar(self, shift: U256) -> I256 {
+        if shift >= U256::from(256) {
+            return if self.is_negative() { I256::MINUS_ONE } else { I256::default() };
+        }
+        let shift = shift.as_usize();
+        if self.is_negative() {
+            I256(!(!self.0 >> shift))
+        } else {
+            I256(self.0 >> shift)
+        }
+    }
+
+    /// Extends the sign bit of byte `byte_index` (0 = least significant)
+    /// through the upper bytes of `value`.
+    pub fn sign_extend(byte_index: U256, value: U256) -> I256 {
+        if byte_index >= U256::from(31) {
+            return I256(value);
+        }
+        let bit = byte_index.as_usize() * 8 + 7;
+        let mask = (U256::one() << bit) - U256::one();
+        if value.bit(bit) {
+            I256(value | !mask)
+        } else {
+            I256(value & mask)
+        }
+    }
+}
+
+impl Ord for I256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.is_negative(), other.is_negative()) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            _ => self.0.cmp(&other.0),
+        }
+    }
+}
+
+impl PartialOrd for I256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Signed division truncating towards zero. Division by zero yields zero
+/// and `MIN / -1` wraps back to `MIN`.
+impl Div for I256 {
+    type Output = I256;
+
+    fn div(self, rhs: I256) -> I256 {
+        if rhs.is_zero() {
+            return I256::default();
+        }
+        if self == I256::MIN && rhs == I256::MINUS_ONE {
+            return I256::MIN;
+        }
+        let quotient = self.unsigned_abs() / rhs.unsigned_abs();
+        I256::from_magnitude(quotient, self.is_negative() != rhs.is_negative())
+    }
+}
+
+/// Signed remainder whose sign follows the dividend. A zero divisor yields
+/// zero.
+impl Rem for I256 {
+    type Output = I256;
+
+    fn rem(self, rhs: I256) -> I256 {
+        if rhs.is_zero() {
+            return I256::default();
+        }
+        let remainder = self.unsigned_abs() % rhs.unsigned_abs();
+        I256::from_magnitude(remainder, self.is_negative())
+    }
+}
+
+impl From<U256> for I256 {
+    fn from(value: U256) -> Self {
+        I256(value)
+    }
+}
+
+impl From<I256> for U256 {
+    fn from(value: I256) -> Self {
+        value.0
+    }
+}
+
+fn two_complement(value: U256) -> U256 {
+    (!value).overflowing_add(U256::one()).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(value: i64) -> I256 {
+        let magnitude = U256::from(value.unsigned_abs());
+        I256::from_magnitude(magnitude, value < 0)
+    }
+
+    #[test]
+    fn division_truncates_towards_zero() {
+        assert_eq!(int(7) / int(2), int(3));
+        assert_eq!(int(-7) / int(2), int(-3));
+        assert_eq!(int(7) / int(-2), int(-3));
+        assert_eq!(int(-7) / int(-2), int(3));
+        assert_eq!(int(-7) / int(0), int(0));
+    }
+
+    #[test]
+    fn min_divided_by_minus_one_wraps() {
+        assert_eq!(I256::MIN / I256::MINUS_ONE, I256::MIN);
+        assert_eq!(I256::MIN % I256::MINUS_ONE, int(0));
+        assert_eq!(I256::MIN / int(1), I256::MIN);
+    }
+
+    #[test]
+    fn remainder_takes_the_sign_of_the_dividend() {
+        assert_eq!(int(7) % int(3), int(1));
+        assert_eq!(int(-7) % int(3), int(-1));
+        assert_eq!(int(7) % int(-3), int(1));
+        assert_eq!(int(-7) % int(-3), int(-1));
+        assert_eq!(int(-7) % int(0), int(0));
+    }
+
+    #[test]
+    fn sar_keeps_the_sign() {
+        assert_eq!(int(-8).sar(U256::from(1)), int(-4));
+        assert_eq!(int(-1).sar(U256::from(255)), int(-1));
+        assert_eq!(I256::MIN.sar(U256::from(255)), int(-1));
+        assert_eq!(int(8).sar(U256::from(3)), int(1));
+    }
+
+    #[test]
+    fn sar_by_256_or_more_leaves_only_the_sign() {
+        for shift in [U256::from(256), U256::from(1000), U256::MAX] {
+            assert_eq!(int(-5).sar(shift), I256::MINUS_ONE);
+            assert_eq!(I256::MIN.sar(shift), I256::MINUS_ONE);
+            assert_eq!(int(5).sar(shift), int(0));
+        }
+    }
+
+    #[test]
+    fn sign_extend_copies_the_sign_bit_of_the_byte() {
+        assert_eq!(I256::sign_extend(U256::zero(), U256::from(0xff)), int(-1));
+        assert_eq!(I256::sign_extend(U256::zero(), U256::from(0x7f)), int(0x7f));
+        assert_eq!(I256::sign_extend(U256::one(), U256::from(0x12_8000)), int(-0x8000));
+        assert_eq!(I256::sign_extend(U256::zero(), U256::from(0x1234)), int(0x34));
+    }
+
+    #[test]
+    fn sign_extend_from_byte_31_or_more_is_a_no_op() {
+        let value = U256::from(0x80) << 240;
+        for byte_index in [U256::from(31), U256::from(32), U256::MAX] {
+            assert_eq!(I256::sign_extend(byte_index, value), I256(value));
+            assert_eq!(I256::sign_extend(byte_index, U256::from(0xff)), int(0xff));
+        }
+    }
+
+    #[test]
+    fn ordering_puts_negatives_first() {
+        assert!(I256::MIN < int(-1));
+        assert!(int(-1) < int(0));
+        assert!(int(0) < int(1));
+        assert!(int(-2) < int(-1));
+    }
+}
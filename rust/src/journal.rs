@@ -0,0 +1,316 @@
+use std::collections::{HashMap, HashSet};
+use primitive_types::U256;
+
+use crate::*;
+
+/// A state change, recorded with what is needed to undo it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JournalEntry {
+    StorageChanged { address: Address, key: U256, had_value: U256 },
+    BalanceChanged { address: Address, had_balance: U256 },
+    NonceChanged { address: Address, had_nonce: u64 },
+    /// `address` held no account until a change brought an empty one into
+    /// existence.
+    AccountAdded { address: Address },
+    /// A contract was created at `address`, which held no code and a zero
+    /// nonce before.
+    AccountCreated { address: Address },
+    /// `address` executed SELFDESTRUCT and is removed at the end of the
+    /// transaction.
+    AccountDestroyed { address: Address },
+    CodeChanged { address: Address },
+    LogEmitted,
+    TransientStorageChanged { address: Address, key: U256, had_value: U256 },
+    /// `address` became warm (EIP-2929).
+    AccountWarmed { address: Address },
+    /// Slot `key` of `address` became warm (EIP-2929).
+    StorageWarmed { address: Address, key: U256 },
+}
+
+/// Position in the journal to revert to or commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct JournalCheckpoint {
+    entries: usize,
+}
+
+/// The state an execution works on, with a journal of every change so that
+/// the effects of a failed call can be undone.
+#[derive(Debug, Clone, Default)]
+pub struct JournaledState<DB> {
+    db: CacheDB<DB>,
+    entries: Vec<JournalEntry>,
+    logs: Vec<Log>,
+    /// Contracts created in the current transaction.
+    created_accounts: HashSet<Address>,
+    /// Contracts to remove at the end of the current transaction.
+    destroyed_accounts: HashSet<Address>,
+    /// Storage that lives for one transaction (EIP-1153).
+    transient_storage: HashMap<(Address, U256), U256>,
+    /// Addresses accessed in the current transaction.
+    warm_accounts: HashSet<Address>,
+    /// Storage slots accessed in the current transaction.
+    warm_slots: HashSet<(Address, U256)>,
+    depth: usize,
+}
+
+impl<DB: Database> JournaledState<DB> {
+    pub fn new(db: DB) -> Self {
+        Self {
+            db: CacheDB::new(db),
+            entries: Vec::new(),
+            logs: Vec::new(),
+            created_accounts: HashSet::new(),
+            destroyed_accounts: HashSet::new(),
+            transient_storage: HashMap::new(),
+            warm_accounts: HashSet::new(),
+            warm_slots: HashSet::new(),
+            depth: 0,
+        }
+    }
+
+    /// Puts `account` at `address` without journaling, for setting up the
+    /// state before execution.
+    pub fn insert_account(&mut self, address: Address, account: Account) {
+        self.db.insert_account(address, account);
+    }
+
+    /// The state with every finished transaction applied.
+    pub fn db(&self) -> &CacheDB<DB> {
+        &self.db
+    }
+
+    pub fn db_mut(&mut self) -> &mut CacheDB<DB> {
+        &mut self.db
+    }
+
+    pub fn account(&mut self, address: Address) -> Result<Option<&Account>, DB::Error> {
+        self.db.account(address)
+    }
+
+    pub fn block_hash(&mut self, number: u64) -> Result<B256, DB::Error> {
+        self.db.block_hash(number)
+    }
+
+    pub fn sload(&mut self, address: Address, key: U256) -> Result<U256, DB::Error> {
+        self.db.sload(address, key)
+    }
+
+    pub fn original_value(&mut self, address: Address, key: U256) -> Result<U256, DB::Error> {
+        self.db.original_value(address, key)
+    }
+
+    pub fn sstore(&mut self, address: Address, key: U256, value: U256) -> Result<(), DB::Error> {
+        self.ensure_account(address)?;
+        let had_value = self.db.sload(address, key)?;
+        self.db.sstore(address, key, value)?;
+        self.entries.push(JournalEntry::StorageChanged { address, key, had_value });
+        Ok(())
+    }
+
+    /// Moves `value` from `from` to `to`. A sender without enough balance is
+    /// left at zero; callers check affordability first.
+    pub fn transfer(&mut self, from: Address, to: Address, value: U256) -> Result<(), DB::Error> {
+        if value.is_zero() {
+            return Ok(());
+        }
+        self.ensure_account(from)?;
+        let sender = self.db.loaded_account_mut(from);
+        let had_balance = sender.balance;
+        sender.balance = had_balance.saturating_sub(value);
+        self.entries.push(JournalEntry::BalanceChanged { address: from, had_balance });
+
+        self.ensure_account(to)?;
+        let recipient = self.db.loaded_account_mut(to);
+        let had_balance = recipient.balance;
+        recipient.balance = had_balance.saturating_add(value);
+        self.entries.push(JournalEntry::BalanceChanged { address: to, had_balance });
+        Ok(())
+    }
+
+    pub fn inc_nonce(&mut self, address: Address) -> Result<(), DB::Error> {
+        self.ensure_account(address)?;
+        let account = self.db.loaded_account_mut(address);
+        let had_nonce = account.nonce;
+        account.nonce += 1;
+        self.entries.push(JournalEntry::NonceChanged { address, had_nonce });
+        Ok(())
+    }
+
+    /// Marks `address` as a new contract starting at `nonce`.
+    pub fn create_account(&mut self, address: Address, nonce: u64) -> Result<(), DB::Error> {
+        self.ensure_account(address)?;
+        self.db.loaded_account_mut(address).nonce = nonce;
+        self.created_accounts.insert(address);
+        self.entries.push(JournalEntry::AccountCreated { address });
+        Ok(())
+    }
+
+    /// Whether `address` was created in the current transaction.
+    pub fn is_created(&self, address: Address) -> bool {
+        self.created_accounts.contains(&address)
+    }
+
+    /// Sends the whole balance of `address` to `beneficiary` and, if
+    /// `destroy` is set, removes the account at the end of the transaction.
+    /// A destroyed account that names itself as beneficiary loses its
+    /// balance. Returns whether the account was already marked destroyed.
+    pub fn selfdestruct(&mut self, address: Address, beneficiary: Address, destroy: bool) -> Result<bool, DB::Error> {
+        let balance = self.account(address)?.map(|account| account.balance).unwrap_or_default();
+        if beneficiary != address {
+            self.transfer(address, beneficiary, balance)?;
+        } else if destroy && !balance.is_zero() {
+            self.db.loaded_account_mut(address).balance = U256::zero();
+            self.entries.push(JournalEntry::BalanceChanged { address, had_balance: balance });
+        }
+
+        if !destroy {
+            return Ok(false);
+        }
+        let was_destroyed = !self.destroyed_accounts.insert(address);
+        if !was_destroyed {
+            self.entries.push(JournalEntry::AccountDestroyed { address });
+        }
+        Ok(was_destroyed)
+    }
+
+    pub fn set_code(&mut self, address: Address, code: Bytes) -> Result<(), DB::Error> {
+        self.ensure_account(address)?;
+        self.db.loaded_account_mut(address).set_code(code);
+        self.entries.push(JournalEntry::CodeChanged { address });
+        Ok(())
+    }
+
+    /// Creates an empty account at `address` if there is none, so that a
+    /// revert removes it again.
+    fn ensure_account(&mut self, address: Address) -> Result<(), DB::Error> {
+        if self.db.account(address)?.is_none() {
+            self.db.loaded_account_mut(address);
+            self.entries.push(JournalEntry::AccountAdded { address });
+        }
+        Ok(())
+    }
+
+    pub fn log(&mut self, log: Log) {
+        self.logs.push(log);
+        self.entries.push(JournalEntry::LogEmitted);
+    }
+
+    pub fn tload(&self, address: Address, key: U256) -> U256 {
+        self.transient_storage.get(&(address, key)).copied().unwrap_or_default()
+    }
+
+    pub fn tstore(&mut self, address: Address, key: U256, value: U256) {
+        let had_value = if value.is_zero() {
+            self.transient_storage.remove(&(address, key))
+        } else {
+            self.transient_storage.insert((address, key), value)
+        };
+        self.entries.push(JournalEntry::TransientStorageChanged { address, key, had_value: had_value.unwrap_or_default() });
+    }
+
+    /// Marks `address` as accessed. Returns whether it was cold.
+    pub fn access_account(&mut self, address: Address) -> bool {
+        let is_cold = self.warm_accounts.insert(address);
+        if is_cold {
+            self.entries.push(JournalEntry::AccountWarmed { address });
+        }
+        is_cold
+    }
+
+    /// Marks slot `key` of `address` as accessed. Returns whether it was
+    /// cold.
+    pub fn access_slot(&mut self, address: Address, key: U256) -> bool {
+        let is_cold = self.warm_slots.insert((address, key));
+        if is_cold {
+            self.entries.push(JournalEntry::StorageWarmed { address, key });
+        }
+        is_cold
+    }
+
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    /// Ends the transaction: self-destructed accounts are removed, the
+    /// journal, transient storage and the access lists are dropped, so
+    /// changes can no longer be reverted, and the logs it emitted are
+    /// returned.
+    pub fn finalize(&mut self) -> Vec<Log> {
+        self.entries.clear();
+        self.transient_storage.clear();
+        for address in self.destroyed_accounts.drain() {
+            self.db.remove_account(address);
+        }
+        self.created_accounts.clear();
+        self.warm_accounts.clear();
+        self.warm_slots.clear();
+        self.db.reset_original_values();
+        self.depth = 0;
+        std::mem::take(&mut self.logs)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Opens a nested scope whose changes can be reverted as one.
+    pub fn checkpoint(&mut self) -> JournalCheckpoint {
+        self.depth += 1;
+        JournalCheckpoint { entries: self.entries.len() }
+    }
+
+    /// Closes the scope opened by `checkpoint`, keeping its changes. They
+    /// are still undone if an enclosing scope reverts.
+    pub fn commit(&mut self, _checkpoint: JournalCheckpoint) {
+        self.depth -= 1;
+    }
+
+    /// Closes the scope opened by `checkpoint`, undoing every change made
+    /// since, newest first.
+    pub fn revert_to(&mut self, checkpoint: JournalCheckpoint) {
+        self.depth -= 1;
+        let reverted = self.entries.split_off(checkpoint.entries);
+        for entry in reverted.into_iter().rev() {
+            match entry {
+                JournalEntry::StorageChanged { address, key, had_value } => {
+                    self.db.loaded_account_mut(address).sstore(key, had_value);
+                },
+                JournalEntry::BalanceChanged { address, had_balance } => {
+                    self.db.loaded_account_mut(address).balance = had_balance;
+                },
+                JournalEntry::NonceChanged { address, had_nonce } => {
+                    self.db.loaded_account_mut(address).nonce = had_nonce;
+                },
+                JournalEntry::AccountAdded { address } => {
+                    self.db.remove_account(address);
+                },
+                JournalEntry::AccountCreated { address } => {
+                    self.db.loaded_account_mut(address).nonce = 0;
+                    self.created_accounts.remove(&address);
+                },
+                JournalEntry::AccountDestroyed { address } => {
+                    self.destroyed_accounts.remove(&address);
+                },
+                JournalEntry::CodeChanged { address } => {
+                    self.db.loaded_account_mut(address).set_code(Bytes::default());
+                },
+                JournalEntry::LogEmitted => {
+                    self.logs.pop();
+                },
+                JournalEntry::TransientStorageChanged { address, key, had_value } => {
+                    if had_value.is_zero() {
+                        self.transient_storage.remove(&(address, key));
+                    } else {
+                        self.transient_storage.insert((address, key), had_value);
+                    }
+                },
+                JournalEntry::AccountWarmed { address } => {
+                    self.warm_accounts.remove(&address);
+                },
+                JournalEntry::StorageWarmed { address, key } => {
+                    self.warm_slots.remove(&(address, key));
+                },
+            }
+        }
+    }
+}
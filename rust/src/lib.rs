@@ -1,6 +1,28 @@
-use std::{str::FromStr, collections::HashMap, cmp::min};
-use primitive_types::U256;
-use serde::Deserialize;
+use std::{collections::HashMap, cmp::{max, min}, fmt, sync::Arc};
+use primitive_types::{U256, U512};
+
+mod db;
+mod env;
+mod fixture;
+pub mod gas;
+mod i256;
+mod journal;
+mod keccak;
+mod precompile;
+mod primitives;
+mod spec;
+#[cfg(test)]
+mod tests;
+
+pub use db::{BasicAccount, CacheDB, Database, InMemoryDB};
+pub use env::{AccessListItem, BlockEnv, BlockEnvBuilder, CfgEnv, CfgEnvBuilder, Env, TxEnv, TxEnvBuilder};
+pub use fixture::{AccountCode, AccountInfo, Block, StateInfo, Tx};
+pub use gas::{Gas, GasSchedule, DEFAULT_GAS_LIMIT};
+pub use i256::I256;
+pub use journal::{JournalCheckpoint, JournalEntry, JournaledState};
+pub use keccak::{keccak256, KECCAK_EMPTY};
+pub use primitives::{Address, Bytes, HexBytes, ParseHexError, Quantity, B256};
+pub use spec::SpecId;
 
 // Memory
 
@@ -14,82 +36,117 @@ impl Memory {
         Self { memory: Vec::with_capacity(4 * 1024) }
     }
 
-    pub fn store(&mut self, offset: usize, value: &[u8]) {
-        if offset + value.len() > self.memory.len() {
-            self.memory.resize(offset + value.len(), 0);
+    /// Current size in bytes, always a multiple of 32.
+    pub fn size(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Cost of growing memory so that it covers `offset..offset + size`.
+    /// Memory of `w` words costs `3*w + w²/512`; the result is the
+    /// difference between the new and the current total.
+    pub fn expansion_cost(&self, offset: usize, size: usize) -> u64 {
+        if size == 0 {
+            return 0;
         }
-        self.memory[offset..(value.len() + offset)].copy_from_slice(value);
+        let current_words = gas::words(self.memory.len());
+        let new_words = gas::words(offset + size);
+        if new_words <= current_words {
+            return 0;
+        }
+        gas::memory_cost(new_words) - gas::memory_cost(current_words)
     }
 
-    pub fn store8(&mut self, offset: usize, value: u8) {
-        if offset >= self.memory.len() {
-            self.memory.resize(offset + 1, 0);
+    /// Grows memory in whole 32-byte words so that it covers
+    /// `offset..offset + size`. Zero-sized ranges never expand memory.
+    pub fn expand(&mut self, offset: usize, size: usize) {
+        if size == 0 {
+            return;
+        }
+        let end = (offset + size).div_ceil(32) * 32;
+        if end > self.memory.len() {
+            self.memory.resize(end, 0);
         }
-        self.memory[offset] = value;
     }
 
-    pub fn load(&self, offset: usize, size: usize) -> [u8; 32] {
-        let mut result = [0u8; 32];
-        let end = std::cmp::min(self.memory.len(), offset + size);
-        result[..end-offset].copy_from_slice(&self.memory[offset..end]);
-        result
+    pub fn store(&mut self, offset: usize, value: &[u8]) {
+        self.expand(offset, value.len());
+        self.memory[offset..(value.len() + offset)].copy_from_slice(value);
     }
 
-    pub fn size(&self) -> usize {
-        self.memory.len()
+    /// Copies `size` bytes from `source` to `destination`. The ranges may
+    /// overlap.
+    pub fn copy_within(&mut self, source: usize, destination: usize, size: usize) {
+        if size == 0 {
+            return;
+        }
+        self.expand(max(source, destination), size);
+        self.memory.copy_within(source..source + size, destination);
     }
-}
 
+    pub fn store8(&mut self, offset: usize, value: u8) {
+        self.expand(offset, 1);
+        self.memory[offset] = value;
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct Tx {
-    pub from: Option<String>,
-    pub to: Option<String>,
-    pub origin: Option<String>,
-    pub gasprice: Option<String>,
-    pub value: Option<String>,
-    pub data: Option<String>,
-}
+    /// Reads `size` bytes at `offset`. Bytes past the end of memory read as
+    /// zero.
+    pub fn slice(&self, offset: usize, size: usize) -> Vec<u8> {
+        let mut result = vec![0u8; size];
+        if offset < self.memory.len() {
+            let end = min(self.memory.len(), offset + size);
+            result[..end - offset].copy_from_slice(&self.memory[offset..end]);
+        }
+        result
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct Block {
-    pub coinbase: Option<String>,
-    pub timestamp: Option<String>,
-    pub number: Option<String>,
-    pub difficulty: Option<String>,
-    pub gaslimit: Option<String>,
-    pub chainid: Option<String>,
+    pub fn load(&self, offset: usize) -> U256 {
+        U256::from_big_endian(&self.slice(offset, 32))
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct StateInfo(HashMap<String, AccountInfo>);
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct AccountInfo {
-    pub balance: Option<String>,
-    pub code: Option<AccountCode>,
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct AccountCode {
-    pub bin: Option<String>,
-}
 
 // Account
 
-pub type State = HashMap<String, Account>;
+pub type State = HashMap<Address, Account>;
 
+#[derive(Debug, Clone)]
 pub struct Account {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code: Bytes,
+    /// Keccak-256 of `code`.
+    pub code_hash: B256,
     pub storage: HashMap<U256, StorageSlot>,
 }
 
 impl Account {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Account {
+            nonce: 0,
+            balance: U256::zero(),
+            code: Bytes::default(),
+            code_hash: KECCAK_EMPTY,
             storage: HashMap::default(),
         }
     }
 
+    pub fn set_code(&mut self, code: Bytes) {
+        self.code_hash = keccak256(&code);
+        self.code = code;
+    }
+
+    /// Whether the account has no code, a zero nonce and a zero balance
+    /// (EIP-161).
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0 && self.balance.is_zero() && self.code.is_empty()
+    }
+
     fn sstore(&mut self, key: U256, value: U256) {
         if let Some(storage) = self.storage.get_mut(&key) {
             storage.value = value
@@ -108,622 +165,1397 @@ impl Account {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct StorageSlot {
-    pub value: U256
+    /// Value at the start of the transaction.
+    pub original_value: U256,
+    pub value: U256,
+}
+
+impl Default for Account {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl StorageSlot {
     fn new(value: U256) -> Self {
         Self {
+            original_value: value,
             value,
         }
     }
+
+    pub fn is_changed(&self) -> bool {
+        self.original_value != self.value
+    }
 }
 
-// EVM
+// Errors
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvmError {
+    StackUnderflow,
+    StackOverflow,
+    InvalidJump,
+    InvalidOpcode(u8),
+    OutOfGas,
+    OutOfOffset,
+    WriteProtection,
+    InitCodeSizeLimit,
+    CodeSizeLimit,
+    InvalidCodePrefix,
+    /// The sender cannot pay the value of the transaction.
+    InsufficientBalance,
+    /// A call reached a precompiled contract that is not implemented.
+    UnsupportedPrecompile(Address),
+}
+
+impl fmt::Display for EvmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvmError::StackUnderflow => write!(f, "stack underflow"),
+            EvmError::StackOverflow => write!(f, "stack overflow"),
+            EvmError::InvalidJump => write!(f, "invalid jump destination"),
+            EvmError::InvalidOpcode(opcode) => write!(f, "invalid opcode {:#04x}", opcode),
+            EvmError::OutOfGas => write!(f, "out of gas"),
+            EvmError::OutOfOffset => write!(f, "offset out of bounds"),
+            EvmError::WriteProtection => write!(f, "state modification in static context"),
+            EvmError::InitCodeSizeLimit => write!(f, "initcode exceeds the size limit"),
+            EvmError::CodeSizeLimit => write!(f, "contract code exceeds the size limit"),
+            EvmError::InvalidCodePrefix => write!(f, "contract code starts with 0xef"),
+            EvmError::InsufficientBalance => write!(f, "insufficient balance for transfer"),
+            EvmError::UnsupportedPrecompile(address) => write!(f, "precompile {} is not supported", address),
+        }
+    }
+}
+
+impl std::error::Error for EvmError {}
+
+// Result
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaltReason {
+    Stop,
+    Return,
+    Revert,
+    Error(EvmError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<U256>,
+    pub data: Vec<u8>,
+}
 
 #[derive(Debug, Clone)]
-pub struct RustEVM {
+pub struct ExecutionResult {
+    pub halt_reason: HaltReason,
+    pub return_data: Vec<u8>,
+    /// Final stack, top of the stack first.
+    pub stack: Vec<U256>,
+    pub logs: Vec<Log>,
+    /// Gas spent, including the intrinsic gas of the transaction and net
+    /// of refunds.
+    pub gas_used: u64,
+    /// Gas paid back from the refund counter, already taken off `gas_used`.
+    pub gas_refunded: u64,
+    pub memory: Vec<u8>,
+}
+
+impl HaltReason {
+    pub fn is_success(&self) -> bool {
+        matches!(self, HaltReason::Stop | HaltReason::Return)
+    }
+}
+
+impl ExecutionResult {
+    pub fn is_success(&self) -> bool {
+        self.halt_reason.is_success()
+    }
+}
+
+// Stack
+
+const STACK_LIMIT: usize = 1024;
+
+#[derive(Debug, Clone, Default)]
+struct Stack {
+    data: Vec<U256>,
+}
+
+impl Stack {
+    fn new() -> Self {
+        Self { data: Vec::with_capacity(STACK_LIMIT) }
+    }
+
+    fn pop(&mut self) -> Result<U256, EvmError> {
+        self.data.pop().ok_or(EvmError::StackUnderflow)
+    }
+
+    fn push(&mut self, value: U256) -> Result<(), EvmError> {
+        if self.data.len() >= STACK_LIMIT {
+            return Err(EvmError::StackOverflow);
+        }
+        self.data.push(value);
+        Ok(())
+    }
+
+    /// Pushes a copy of the `n`th item from the top, 1-based.
+    fn dup(&mut self, n: usize) -> Result<(), EvmError> {
+        if n == 0 || self.data.len() < n {
+            return Err(EvmError::StackUnderflow);
+        }
+        let value = self.data[self.data.len() - n];
+        self.push(value)
+    }
+
+    /// Exchanges the top item with the one `n` positions below it.
+    fn swap(&mut self, n: usize) -> Result<(), EvmError> {
+        if n == 0 || self.data.len() <= n {
+            return Err(EvmError::StackUnderflow);
+        }
+        let top = self.data.len() - 1;
+        self.data.swap(top, top - n);
+        Ok(())
+    }
+}
+
+// Analysis
+
+/// Bitmap of the JUMPDEST positions in a piece of code that are real
+/// instructions rather than bytes inside PUSH immediates.
+#[derive(Debug, Clone)]
+pub struct JumpTable {
+    bits: Vec<u64>,
+}
+
+impl JumpTable {
+    pub fn analyze(code: &[u8]) -> Self {
+        let mut bits = vec![0u64; code.len().div_ceil(64)];
+        let mut pc = 0;
+        while pc < code.len() {
+            let opcode = code[pc];
+            if opcode == JUMPDEST {
+                bits[pc / 64] |= 1 << (pc % 64);
+            } else if (PUSH1..=PUSH32).contains(&opcode) {
+                pc += (opcode - PUSH1 + 1) as usize;
+            }
+            pc += 1;
+        }
+        Self { bits }
+    }
+
+    pub fn is_valid(&self, pc: usize) -> bool {
+        self.bits.get(pc / 64).is_some_and(|word| word & (1 << (pc % 64)) != 0)
+    }
+}
+
+// Frame
+
+/// Maximum nesting of message calls.
+const CALL_DEPTH_LIMIT: usize = 1024;
+/// Largest deployable contract by default (EIP-170).
+const MAX_CODE_SIZE: usize = 0x6000;
+
+struct Frame {
+    code: Arc<[u8]>,
+    jump_table: Arc<JumpTable>,
+    pc: usize,
+    stack: Stack,
     memory: Memory,
+    gas: Gas,
+    /// Account whose storage and balance the code operates on.
+    address: Address,
+    caller: Address,
+    value: U256,
+    input: Vec<u8>,
+    depth: usize,
+    /// Whether state modifications are forbidden, as inside STATICCALL.
+    is_static: bool,
+    output: Vec<u8>,
+    /// Output of the most recent sub-call (EIP-211).
+    return_data: Vec<u8>,
+    /// Caller memory range that receives the output of this frame.
+    return_range: (usize, usize),
+    /// Journal position to revert to if the frame fails.
+    checkpoint: JournalCheckpoint,
+    /// Address being deployed, for frames running initcode.
+    created_address: Option<Address>,
+    /// Precompiled contract run in place of `code`.
+    precompile: Option<Address>,
+}
+
+impl Frame {
+    fn new(code: Arc<[u8]>, jump_table: Arc<JumpTable>, gas_limit: u64) -> Self {
+        Self {
+            code,
+            jump_table,
+            pc: 0,
+            stack: Stack::new(),
+            memory: Memory::new(),
+            gas: Gas::new(gas_limit),
+            address: Address::ZERO,
+            caller: Address::ZERO,
+            value: U256::zero(),
+            input: Vec::new(),
+            depth: 0,
+            is_static: false,
+            output: Vec::new(),
+            return_data: Vec::new(),
+            return_range: (0, 0),
+            checkpoint: JournalCheckpoint::default(),
+            created_address: None,
+            precompile: None,
+        }
+    }
+}
+
+/// A message call requested by one of the CALL opcodes. Gas for it has
+/// already been deducted from the calling frame.
+struct CallInputs {
+    /// Account whose code is executed.
+    code_address: Address,
+    /// Account the callee runs as.
+    address: Address,
+    caller: Address,
+    value: U256,
+    input: Vec<u8>,
+    gas_limit: u64,
+    is_static: bool,
+    return_range: (usize, usize),
+    /// Whether `value` moves from `caller` to `address`. DELEGATECALL only
+    /// passes its value along.
+    transfers_value: bool,
+}
+
+/// A contract creation requested by CREATE or CREATE2. Gas for it has
+/// already been deducted from the creating frame.
+struct CreateInputs {
+    value: U256,
+    init_code: Vec<u8>,
+    /// Present for CREATE2.
+    salt: Option<U256>,
+    gas_limit: u64,
+}
+
+/// What the interpreter loop does after an instruction.
+enum Control {
+    Continue,
+    Call(Box<CallInputs>),
+    Create(Box<CreateInputs>),
+    Halt(HaltReason),
+}
+
+/// Why an instruction did not complete.
+enum StepError<E> {
+    /// The current frame halts exceptionally.
+    Evm(EvmError),
+    /// The database failed to read state; the whole execution is aborted.
+    Database(E),
+}
+
+impl<E> From<EvmError> for StepError<E> {
+    fn from(error: EvmError) -> Self {
+        StepError::Evm(error)
+    }
+}
+
+/// Charges for and performs the expansion of memory to `offset..offset + size`.
+fn expand_memory(gas: &mut Gas, memory: &mut Memory, offset: usize, size: usize) -> Result<(), EvmError> {
+    gas.record_cost(memory.expansion_cost(offset, size))?;
+    memory.expand(offset, size);
+    Ok(())
+}
+
+fn jump_destination(jump_table: &JumpTable, destination: U256) -> Result<usize, EvmError> {
+    if destination > U256::from(MAX_OFFSET) || !jump_table.is_valid(destination.as_usize()) {
+        return Err(EvmError::InvalidJump);
+    }
+    Ok(destination.as_usize())
+}
+
+/// Most pieces of code whose JUMPDEST analysis is kept, so that a
+/// long-lived interpreter does not grow with every initcode it runs.
+const JUMP_TABLE_CACHE_SIZE: usize = 1024;
+
+/// Number of recent blocks whose hashes BLOCKHASH can return.
+const BLOCK_HASH_HISTORY: u64 = 256;
+
+/// Largest memory offset or size the interpreter accepts.
+const MAX_OFFSET: u64 = u32::MAX as u64;
+
+fn as_offset(value: U256) -> Result<usize, EvmError> {
+    if value > U256::from(MAX_OFFSET) {
+        return Err(EvmError::OutOfOffset);
+    }
+    Ok(value.as_usize())
+}
+
+/// Converts a stack offset/size pair into a memory range. Zero-sized ranges
+/// never touch memory, so their offset is not checked.
+fn memory_range(offset: U256, size: U256) -> Result<(usize, usize), EvmError> {
+    if size.is_zero() {
+        return Ok((0, 0));
+    }
+    Ok((as_offset(offset)?, as_offset(size)?))
+}
+
+/// Copies `size` bytes of `data` from `data_offset` into memory at
+/// `memory_offset`, charging for expansion and the copy. Bytes past the end
+/// of `data` are written as zero.
+fn copy_to_memory(gas: &mut Gas, memory: &mut Memory, memory_offset: U256, data: &[u8], data_offset: U256, size: U256) -> Result<(), EvmError> {
+    let (memory_offset, size) = memory_range(memory_offset, size)?;
+    expand_memory(gas, memory, memory_offset, size)?;
+    gas.record_cost(gas::copy_cost(size))?;
+    memory.store(memory_offset, &padded_slice(data, data_offset, size));
+    Ok(())
+}
+
+/// Copies `size` bytes of `data` starting at `offset`, zero-padding past the end.
+fn padded_slice(data: &[u8], offset: U256, size: usize) -> Vec<u8> {
+    let mut result = vec![0u8; size];
+    if offset < U256::from(data.len()) {
+        let offset = offset.as_usize();
+        let end = min(data.len(), offset + size);
+        result[..end - offset].copy_from_slice(&data[offset..end]);
+    }
+    result
+}
+
+/// Computes `base ^ exponent mod 2^256` by square-and-multiply.
+fn wrapping_pow(mut base: U256, mut exponent: U256) -> U256 {
+    let mut result = U256::one();
+    while !exponent.is_zero() {
+        if exponent.bit(0) {
+            result = result.overflowing_mul(base).0;
+        }
+        exponent >>= 1;
+        base = base.overflowing_mul(base).0;
+    }
+    result
+}
+
+/// Truncates a 512-bit intermediate that is known to fit into 256 bits.
+fn low_u256(value: U512) -> U256 {
+    let mut bytes = [0u8; 64];
+    value.to_big_endian(&mut bytes);
+    U256::from_big_endian(&bytes[32..])
+}
+
+/// Address of a contract created by CREATE: the last 20 bytes of
+/// `keccak256(rlp([sender, nonce]))`.
+fn create_address(sender: Address, nonce: u64) -> Address {
+    let nonce_bytes = nonce.to_be_bytes();
+    let nonce_bytes = &nonce_bytes[nonce.leading_zeros() as usize / 8..];
+    let mut payload = vec![0x80 + 20];
+    payload.extend_from_slice(&sender.0);
+    match nonce_bytes {
+        [byte] if *byte < 0x80 => payload.push(*byte),
+        bytes => {
+            payload.push(0x80 + bytes.len() as u8);
+            payload.extend_from_slice(bytes);
+        },
+    }
+
+    let mut encoded = vec![0xc0 + payload.len() as u8];
+    encoded.extend_from_slice(&payload);
+    Address::from_word(keccak256(&encoded).into())
+}
+
+/// Address of a contract created by CREATE2: the last 20 bytes of
+/// `keccak256(0xff ++ sender ++ salt ++ keccak256(initcode))`.
+fn create2_address(sender: Address, salt: U256, init_code_hash: &B256) -> Address {
+    let mut buffer = [0u8; 85];
+    buffer[0] = 0xff;
+    buffer[1..21].copy_from_slice(&sender.0);
+    salt.to_big_endian(&mut buffer[21..53]);
+    buffer[53..].copy_from_slice(init_code_hash.as_bytes());
+    Address::from_word(keccak256(&buffer).into())
+}
+
+// EVM
+
+#[derive(Debug, Clone, Default)]
+pub struct RustEVM<DB = InMemoryDB> {
+    /// Environment of the current execution.
+    env: Env,
+    /// Gas costs of `env.cfg.spec`.
+    gas_schedule: GasSchedule,
+    /// State as seen by this execution: reads go through to the database,
+    /// writes stay here.
+    state: JournaledState<DB>,
+    /// JUMPDEST analysis of recently executed code, by code hash. Emptied
+    /// once it holds `JUMP_TABLE_CACHE_SIZE` entries.
+    jump_tables: HashMap<B256, Arc<JumpTable>>,
 }
 
 impl RustEVM {
     pub fn new() -> Self {
-        RustEVM { memory: Memory::new() }
+        Self::with_db(InMemoryDB::new())
     }
+}
 
-    pub fn evaluate(mut self, code: &[u8], tx: &Option<Tx>, state: &Option<StateInfo>, block: &Option<Block>) -> Vec<U256> {
+impl<DB: Database> RustEVM<DB> {
+    /// An interpreter that reads state from `db`.
+    pub fn with_db(db: DB) -> Self {
+        RustEVM {
+            env: Env::default(),
+            gas_schedule: GasSchedule::default(),
+            state: JournaledState::new(db),
+            jump_tables: HashMap::new(),
+        }
+    }
 
-        let mut account = Account::new();
+    /// State left by the transactions executed so far, on top of the
+    /// database.
+    pub fn db(&self) -> &CacheDB<DB> {
+        self.state.db()
+    }
 
-        let mut stack: Vec<U256> = Vec::new();
+    /// Like `db`, for loading accounts or changing state between
+    /// transactions.
+    pub fn db_mut(&mut self) -> &mut CacheDB<DB> {
+        self.state.db_mut()
+    }
 
-        let mut opcode: u8 = 0;
-        
-        let mut pc: usize = 0;
+    /// Runs `code` as the transaction `env.tx` in the account `env.tx.to`,
+    /// on the state left by earlier transactions, after moving `env.tx.value`
+    /// there from the caller. Fails only if the database does, leaving the
+    /// state as it was; how the code itself halted is in the result.
+    pub fn evaluate(&mut self, code: &[u8], env: Env) -> Result<ExecutionResult, DB::Error> {
+        self.gas_schedule = GasSchedule::new(env.cfg.spec);
+        self.env = env;
 
-        while pc < code.len() {
-                opcode = code[pc];
+        let code = Bytes::from(code);
+        let jump_table = self.jump_table(&code, &keccak256(&code));
+        let gas_limit = if self.env.cfg.unlimited_gas { u64::MAX } else { self.env.tx.gas_limit };
 
-                println!("starting operation");
-                match opcode {
-                    STOP => {
-                        pc += 1;
-                        break;
-                    },
-                    ADD => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        if let (Some(a), Some(b)) = (a, b) {
-                            let result = a + b;
-                            stack.push(result);
-                            pc += 1;
-                        }
-                    },
-                    POP => {
-                        stack.pop();
-                    },
-                    MUL => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        if let (Some(a), Some(b)) = (a, b) {
-                            let result = a * b;
-                            stack.push(result);
-                            pc += 1;
-                        }
-                    },
-                    SUB => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        if let (Some(a), Some(b)) = (a, b) {
-                            let result = a - b;
-                            stack.push(result);
-                            pc += 1;
-                        }
-                    },
-                    DIV | SDIV => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            if b == U256::from(0) {
-                                stack.push(U256::from(0));
-                                pc += 1;
-                                continue
-                            }
-
-                            let result = a / b;
-                            stack.push(result);
-                            pc += 1;
-                        }
-                    },
-                    MOD | SMOD => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            if b == U256::from(0) {
-                                stack.push(U256::from(0));
-                                pc += 1;
-                                continue
-                            }
-
-                            let result = a % b;
-                            stack.push(result);
-                            pc += 1;
-                        }
-                    },
-                    LT => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            println!("a {}", a);
-                            println!("b {}", b);
-                            let boolean = if a < b { U256::from(1) } else { U256::from(0) };
-                            stack.push(boolean);
-                            pc += 1;
-                        }
-                    },
-                    SLT => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            println!("a {}", a);
-                            println!("b {}", b);
-                            let boolean = if a > b { U256::from(1) } else { U256::from(0) };
-                            stack.push(boolean);
-                            pc += 1;
-                        }
-                    },
-                    GT | SGT => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            let boolean = if a > b { U256::from(1) } else { U256::from(0) };
-                            stack.push(boolean);
-                            pc += 1;
-                        }
-                    },
-                    EQ => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            println!("a {}", a);
-                            println!("b {}", b);
-                            let boolean = if a == b { U256::from(1) } else { U256::from(0) };
-                            stack.push(boolean);
-                            pc += 1;
-                        }
-                    },
-                    ISZERO => {
-                        let a = stack.pop();
-                        
-                        if let Some(a) = a {
-                            let boolean = if a == U256::from(0) { U256::from(1) } else { U256::from(0) };
-                            stack.push(boolean);
-                            pc += 1;
-                        }
-                    },
-                    AND => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            println!("a {}", a);
-                            println!("b {}", b);
-                            let result = a & b;
-                            stack.push(result);
-                            pc += 1;
-                        }
-                    },
-                    OR => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            println!("a {}", a);
-                            println!("b {}", b);
-                            let result = a | b;
-                            stack.push(result);
-                            pc += 1;
-                        }
-                    },
-                    XOR => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            println!("a {}", a);
-                            println!("b {}", b);
-                            let result = a ^  b;
-                            stack.push(result);
-                            pc += 1;
-                        }
-                    },
-                    NOT => {
-                        let a = stack.pop();
-                        
-                        if let Some(a) = a {
-                            println!("a {}", a);
-                            let result = !a;
-                            stack.push(result);
-                            pc += 1;
-                        }
-                    },
-                    BYTE => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            println!("a {}", a);
-                            println!("b {}", b);
-                            let result = (b >> (U256::from(248) - a * 8)) & U256::from(255);
-                            // y = (x >> (248 - i * 8)) & 0xFF
-                            println!("result {}", result);
-                            stack.push(result);
-                            pc += 1;
-                        }
-                    },
-                    PUSH32 => {
-                        let data = &code[pc + 1 .. pc + 33];
-                        let value = U256::from(data);
-                        stack.push(value);
-                        pc += 32;
-                    },
-                    PUSH20 => {
-                        let data = &code[pc + 1 .. pc + 21];
-                        let value = U256::from(data);
-                        stack.push(value);
-                        pc += 20;
-                    },
-                    MLOAD => {
-                        let offset = stack.pop();
-                        if let Some(offset) = offset {
-                            println!("loading at offset: {}", offset);
-                            let value = self.memory.load(offset.as_usize(), 32 - offset.as_usize());
-                            let big_value = U256::from_big_endian(&value);
-                            println!("loaded value: {}", big_value);
-                            stack.push(big_value);
-                        }
-                    },
-                    MSTORE => {
-                        let offset = stack.pop();
-                        let value = stack.pop();
-                        if let (Some(offset), Some(value)) = (offset, value) {
-                            println!("saving at offset: {}", offset);
-                            let mut bytes = [0u8; 32];
-                            value.to_big_endian(&mut bytes);
-                            self.memory.store(offset.as_usize(), &bytes);
-                        }
+        let mut frame = Frame::new(code, jump_table, gas_limit);
+        frame.address = self.env.tx.to;
+        frame.caller = self.env.tx.caller;
+        frame.value = self.env.tx.value;
+        frame.input = self.env.tx.data.to_vec();
+        frame.checkpoint = self.state.checkpoint();
+        self.warm_up(&frame);
+
+        let checkpoint = frame.checkpoint;
+        let (frame, halt_reason) = match self.execute(frame) {
+            Ok(outcome) => outcome,
+            Err(error) => {
+                self.state.revert_to(checkpoint);
+                self.state.finalize();
+                return Err(error);
+            },
+        };
+
+        // Changes, logs and refunds of a transaction that did not succeed are
+        // discarded. Refunds are capped against all gas spent, intrinsic gas
+        // included.
+        let mut gas_refunded = 0;
+        if halt_reason.is_success() {
+            self.state.commit(frame.checkpoint);
+            let refunded = frame.gas.refunded().max(0) as u64;
+            gas_refunded = min(refunded, self.gas_schedule.max_refund(frame.gas.spent()));
+        } else {
+            self.state.revert_to(frame.checkpoint);
+        }
+
+        Ok(ExecutionResult {
+            halt_reason,
+            return_data: frame.output,
+            stack: frame.stack.data.into_iter().rev().collect(),
+            logs: self.state.finalize(),
+            gas_used: frame.gas.spent() - gas_refunded,
+            gas_refunded,
+            memory: frame.memory.memory,
+        })
+    }
+
+    /// Charges the intrinsic gas of the transaction and moves its value,
+    /// then runs `root`.
+    fn execute(&mut self, mut root: Frame) -> Result<(Frame, HaltReason), DB::Error> {
+        let intrinsic_gas = self.gas_schedule.intrinsic_gas(self.env.cfg.spec, &self.env.tx.data, &self.env.tx.access_list);
+        if let Err(error) = root.gas.record_cost(intrinsic_gas) {
+            return Ok((root, HaltReason::Error(error)));
+        }
+        if !self.can_afford(root.caller, root.value)? {
+            return Ok((root, HaltReason::Error(EvmError::InsufficientBalance)));
+        }
+        self.state.transfer(root.caller, root.address, root.value)?;
+        self.run(root)
+    }
+
+    /// Runs `root` and every frame it calls into until `root` halts, or
+    /// until the database fails.
+    fn run(&mut self, root: Frame) -> Result<(Frame, HaltReason), DB::Error> {
+        let mut frame = root;
+        let mut callers: Vec<Frame> = Vec::new();
+        let mut control = Control::Continue;
+
+        loop {
+            if let Control::Continue = control {
+                control = match self.step(&mut frame) {
+                    Ok(control) => control,
+                    Err(StepError::Evm(error)) => {
+                        frame.gas.spend_all();
+                        Control::Halt(HaltReason::Error(error))
                     },
-                    MSTORE8 => {
-                        let offset = stack.pop();
-                        let value = stack.pop();
-                        if let (Some(offset), Some(value)) = (offset, value) {
-                            println!("saving at value at offset: {} {}", value, offset);
-                            let mut bytes = [0u8; 32];
-                            value.to_big_endian(&mut bytes);
-                            let last_byte = bytes[31]; // Extract the last byte of the U256 value
-                            self.memory.store8(offset.as_usize(), last_byte);
-                        }
+                    Err(StepError::Database(error)) => return Err(error),
+                };
+            }
+
+            match std::mem::replace(&mut control, Control::Continue) {
+                Control::Continue => {},
+                Control::Call(inputs) => {
+                    match self.call_frame(&mut frame, *inputs)? {
+                        Some(callee) => callers.push(std::mem::replace(&mut frame, callee)),
+                        None => if let Err(error) = frame.stack.push(U256::zero()) {
+                            control = Control::Halt(HaltReason::Error(error));
+                        },
                     }
-                    MSIZE => {
-                        let msize = self.memory.size();
-                        stack.push(U256::from(msize))
+                },
+                Control::Create(inputs) => {
+                    match self.create_frame(&mut frame, *inputs)? {
+                        Some(callee) => callers.push(std::mem::replace(&mut frame, callee)),
+                        None => if let Err(error) = frame.stack.push(U256::zero()) {
+                            control = Control::Halt(HaltReason::Error(error));
+                        },
                     }
-                    JUMP => {
-                        let destination = stack.pop();
-                        if let Some(destination) = destination {
-                            pc = destination.as_usize();
-                        }
-                    },
-                    JUMPI => {
-                        let destination = stack.pop();
-                        let condition = stack.pop();
-                        if let (Some(destination), Some(condition)) = (destination, condition)  {
-                            if condition == U256::from(1) {
-                                pc = destination.as_usize();
-                            }
-                        }
-                    },
-                    PC => {
-                        let pc = pc.clone();
-                        stack.push(U256::from(pc));
-                    },
-                    // "5B" => {
-                    //     // let destination = stack.pop();
-                    //     // if let Some(destination) = destination {
-                    //     //     println!("jump {}", destination);
-                    //     //     pc = destination.as_usize();
-                    //     // }
-                    // },
-                    PUSH1 => {
-                        let next_value = code.get(pc + 1);
-                        if let Some(value) = next_value {
-                            let value = U256::from_big_endian(&[*value]);
-                            let bigint = value;
-                            stack.push(bigint);
-                            pc += 1;
-                        }
-                    },
-                    PUSH2 => {
-                        let data = &code[pc + 1 .. pc + 2];
-                        let value = U256::from(data);
-                        stack.push(value);
-                        pc += 2;
-                    },
-                    DUP1 => {
-                        let latest = stack.pop();
-                        if let Some(value) = latest {
-                            let dup = value.clone();
-                            stack.push(value + dup);
-                            pc += 1;
-                        }
-                    },
-                    DUP2 => {
-                        let second_last = stack.get(stack.len() - 2);
-                        if let Some(value) = second_last {
-                            let dup = value.clone();
-                            stack.push(dup);
-                            pc += 1;
-                        }
-                    },
-                    DUP3 => {
-                        let second_last = stack.get(stack.len() - 3);
-                        if let Some(value) = second_last {
-                            let dup = value.clone();
-                            stack.push(dup);
-                            pc += 1;
-                        }
-                    },
-                    SWAP1 => {
-                        let a = stack.pop();
-                        let b = stack.pop();
-                        
-                        if let (Some(a), Some(b)) = (a, b) {
-                            stack.push(a);
-                            stack.push(b);
-                            pc += 1;
-                        }
-                    },
-                    SWAP3 => {
-                        let b = stack.remove(3);
-                        let a = stack.remove(0);
-                        // let b = stack.get(2);
-                        
-                        stack.insert(0, b);
-                        stack.insert(3, a);
-                        pc += 1;
-                    },
-                    ADDRESS => {
-                        if let Some(address) = tx.as_ref().and_then(|t| t.to.clone()) {
-                            println!("address: {}", address);
-                            stack.push(U256::from_str(&address).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    CALLER => {
-                        if let Some(from) = tx.as_ref().and_then(|t| t.from.clone()) {
-                            println!("from: {}", from);
-                            stack.push(U256::from_str(&from).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    ORIGIN => {
-                        if let Some(origin) = tx.as_ref().and_then(|t| t.origin.clone()) {
-                            println!("origin: {}", origin);
-                            stack.push(U256::from_str(&origin).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    GASPRICE => {
-                        if let Some(gasprice) = tx.as_ref().and_then(|t| t.gasprice.clone()) {
-                            stack.push(U256::from_str(&gasprice).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    CALLVALUE => {
-                        if let Some(gasprice) = tx.as_ref().and_then(|t| t.value.clone()) {
-                            stack.push(U256::from_str(&gasprice).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    CALLDATALOAD => {
-                        let index = stack.pop().unwrap().as_usize(); // TODO: Use i
-
-                        if let Some(data_hex) = tx.as_ref().and_then(|t| t.data.clone()) {
-                            if index < data_hex.len() {
-                                let data = hex::decode(data_hex).expect("Decoding failed");
-                                let have_bytes = min(data.len() - index, 32);
-                                let mut bytes = [0u8; 32];
-                                bytes[..have_bytes].copy_from_slice(&data[index..index + have_bytes]);
-                                // [offset..(value.len() + offset)]
-                                // let data2 = data[i..i - 32];
-                                stack.push(U256::from_big_endian(&bytes))
-                            } else {
-                                stack.push(U256::zero())
-                            }
-                        }
-                        pc += 1;
-                    },
-                    CALLDATASIZE => {
-                        if let Some(data_hex) = tx.as_ref().and_then(|t| t.data.clone()) {
-                            let data = hex::decode(data_hex).expect("Decoding failed");
-                            println!("lenght {}", data.len());
-                            stack.push(U256::from(data.len()))
-                        } else {
-                            stack.push(U256::zero())
-                        }
-                        pc += 1;
-                    },
-                    CALLDATACOPY => {
-                        let memory_offset = stack.pop().unwrap_or_default().as_usize();
-                        let data_offset = stack.pop().unwrap_or_default().as_usize();
-                        let length = stack.pop().unwrap_or_default().as_usize();
-
-                        println!("memory offset {}, data offset: {} length {}", memory_offset, data_offset, length);
-
-                        let tx_data = tx.as_ref().and_then(|t| t.data.clone());
-                        if let Some(data_hex) = tx_data {
-                            let data = hex::decode(data_hex).expect("Decoding failed");
-                            let data_end = min(data_offset, data.len());
-                            self.memory.store(memory_offset, &data[data_end..data_end + length]);
-                        }
-                    },
-                    CODESIZE => {
-                        stack.push(U256::from(code.len()));
-                        pc += 1;
-                    },
-                    CODECOPY => {
-                        let memory_offset = stack.pop().unwrap_or_default().as_usize();
-                        let data_offset = stack.pop().unwrap_or_default().as_usize();
-                        let length = stack.pop().unwrap_or_default().as_usize();
-
-                        let data_end = min(data_offset, code.len());
-                        let code_length = min(length, code.len());
-                        
-                        println!("memory offset {}, data offset: {} length {} data end {}", memory_offset, data_offset, length, data_end);
-
-                        self.memory.store(memory_offset, &code[data_end..data_end + code_length]);
-                        // pc += 1;
-                    },
-                    EXTCODESIZE => {
-                        let Some(address_value) = stack.pop() else {
-                            continue;
-                        };
-                        
-                        let eth_address = self.toAddress(address_value);
-
-                        println!("address: {:?}", eth_address);
-                        println!("state: {:?}", state);
-
-                        if let Some(account) = state.as_ref().and_then(|s| s.0.get(&eth_address.to_lowercase())) {
-                            if let Some(account_code) = account.code.clone() {
-                                let code_hex = account_code.clone().bin.unwrap_or_default();
-                                let code_data = hex::decode(code_hex).unwrap_or_default();
-                                stack.push(U256::from(code_data.len()))
-                            } else {
-                                stack.push(U256::from(0))
-                            }
-                        } else {
-                            stack.push(U256::from(0))
-                        }
-                        pc += 1;
-                    },
-                    EXTCODECOPY => {
-
-                        let Some(address_value) = stack.pop() else {
-                            continue;
-                        };
-                        
-                        let eth_address = self.toAddress(address_value);
-                        
-                        let memory_offset = stack.pop().unwrap_or_default().as_usize();
-                        let data_offset = stack.pop().unwrap_or_default().as_usize();
-                        let length = stack.pop().unwrap_or_default().as_usize();
-
-                        println!("memory offset {}, data offset: {} length {}", memory_offset, data_offset, length);
-
-                        if let Some(account) = state.as_ref().and_then(|s| s.0.get(&eth_address.to_lowercase())) {
-                            if let Some(account_code) = account.code.clone() {
-                                let bin_hex = account_code.bin.unwrap_or_default();
-                                let data = hex::decode(bin_hex).expect("Decoding failed");
-                                let data_end = min(data_offset + length, data.len());
-                                let memory_data_end = memory_offset + (data_end - data_offset);
-                                self.memory.store(memory_offset, &data[memory_offset..memory_data_end]);
-                            }
-                        }
-                    },
-                    BALANCE => {
-                        let Some(address_value) = stack.pop() else {
-                            continue;
-                        };
-                        
-                        let eth_address = self.toAddress(address_value);
-
-                        println!("address: {:?}", eth_address);
-                        println!("state: {:?}", state);
-
-                        if let Some(account) = state.as_ref().and_then(|s| s.0.get(&eth_address.to_lowercase())) {
-                            let balance = account.balance.clone().unwrap();
-                            stack.push(U256::from_str(&balance).unwrap())
-                        } else {
-                            stack.push(U256::from(0))
-                        }
-                        pc += 1;
-                    },
-                    SELFBALANCE => {
-                        let Some(to_address) = tx.as_ref().and_then(|t| t.to.clone()) else {
-                            continue;
-                        };
-                        
-                        if let Some(account) = state.as_ref().and_then(|s| s.0.get(&to_address.to_lowercase())) {
-                            let balance = account.balance.clone().unwrap();
-                            stack.push(U256::from_str(&balance).unwrap())
-                        } else {
-                            stack.push(U256::from(0))
-                        }
-                        pc += 1;
-                    },
-                    COINBASE => {
-                        if let Some(coinbase) = block.as_ref().and_then(|b| b.coinbase.clone()) {
-                            stack.push(U256::from_str(&coinbase).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    TIMESTAMP => {
-                        if let Some(timestamp) = block.as_ref().and_then(|b| b.timestamp.clone()) {
-                            stack.push(U256::from_str(&timestamp).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    DIFFICULTY => {
-                        if let Some(difficulty) = block.as_ref().and_then(|b| b.difficulty.clone()) {
-                            stack.push(U256::from_str(&difficulty).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    NUMBER => {
-                        if let Some(number) = block.as_ref().and_then(|b| b.number.clone()) {
-                            stack.push(U256::from_str(&number).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    CHAINID => {
-                        if let Some(chainid) = block.as_ref().and_then(|b| b.chainid.clone()) {
-                            stack.push(U256::from_str(&chainid).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    GASLIMIT => {
-                        if let Some(gaslimit) = block.as_ref().and_then(|b| b.gaslimit.clone()) {
-                            stack.push(U256::from_str(&gaslimit).unwrap())
-                        }
-                        pc += 1;
-                    },
-                    SSTORE => {
-                        let key = stack.pop().unwrap();
-                        let value = stack.pop().unwrap();
+                },
+                Control::Halt(reason) => {
+                    let Some(caller) = callers.pop() else {
+                        return Ok((frame, reason));
+                    };
+                    let callee = std::mem::replace(&mut frame, caller);
+                    let returned = match callee.created_address {
+                        Some(address) => self.return_from_create(&mut frame, callee, reason, address),
+                        None => self.return_from_call(&mut frame, callee, reason),
+                    };
+                    match returned {
+                        Ok(()) => {},
+                        Err(StepError::Evm(error)) => {
+                            frame.gas.spend_all();
+                            control = Control::Halt(HaltReason::Error(error));
+                        },
+                        Err(StepError::Database(error)) => return Err(error),
+                    }
+                },
+            }
+        }
+    }
 
-                        account.sstore(key, value);
-                        println!("stored key {} value {}", key , value);
-                        // pc += 1;
-                    },
-                    SLOAD => {
-                        let key = stack.pop().unwrap();
+    /// Marks what a transaction has accessed before its first instruction:
+    /// the sender, the recipient, the precompiles, the coinbase since
+    /// Shanghai (EIP-3651) and the entries of its access list.
+    fn warm_up(&mut self, root: &Frame) {
+        self.state.access_account(root.caller);
+        self.state.access_account(root.address);
+        for address in precompile::addresses(self.env.cfg.spec) {
+            self.state.access_account(address);
+        }
+        if self.env.cfg.spec.is_enabled_in(SpecId::Shanghai) {
+            self.state.access_account(self.env.block.coinbase);
+        }
+        for item in &self.env.tx.access_list {
+            self.state.access_account(item.address);
+            for key in &item.storage_keys {
+                self.state.access_slot(item.address, U256::from(*key));
+            }
+        }
+    }
+
+    /// Sets up the frame for a message call, or returns `None` if the call
+    /// fails before any code runs.
+    fn call_frame(&mut self, caller: &mut Frame, inputs: CallInputs) -> Result<Option<Frame>, DB::Error> {
+        if caller.depth >= CALL_DEPTH_LIMIT
+            || (inputs.transfers_value && !self.can_afford(inputs.caller, inputs.value)?) {
+            caller.gas.reclaim(inputs.gas_limit);
+            caller.return_data.clear();
+            return Ok(None);
+        }
 
-                        let value = account.sload(key);
-                        println!("loaded value {}", value);
+        let checkpoint = self.state.checkpoint();
+        if inputs.transfers_value {
+            self.state.transfer(inputs.caller, inputs.address, inputs.value)?;
+        }
 
-                        stack.push(value);
+        let (code, code_hash) = self.state.account(inputs.code_address)?
+            .map(|account| (account.code.clone(), account.code_hash))
+            .unwrap_or_else(|| (Bytes::default(), KECCAK_EMPTY));
+        let jump_table = self.jump_table(&code, &code_hash);
 
-                        pc += 1;
-                    },
-                    RETURN => {
-                        stack.clear();
-                        break
-                    },
-                    REVERT => {
-                        stack.clear();
-                        break
-                    },
-                    _ => {
-                        break
-                    }
+        let mut frame = Frame::new(code, jump_table, inputs.gas_limit);
+        frame.address = inputs.address;
+        frame.caller = inputs.caller;
+        frame.value = inputs.value;
+        frame.input = inputs.input;
+        frame.depth = caller.depth + 1;
+        frame.is_static = inputs.is_static;
+        frame.return_range = inputs.return_range;
+        frame.checkpoint = checkpoint;
+        if precompile::is_precompile(self.env.cfg.spec, inputs.code_address) {
+            frame.precompile = Some(inputs.code_address);
+        }
+        Ok(Some(frame))
+    }
+
+    /// Sets up the frame that runs initcode, or returns `None` if the
+    /// creation fails before any code runs.
+    fn create_frame(&mut self, caller: &mut Frame, inputs: CreateInputs) -> Result<Option<Frame>, DB::Error> {
+        caller.return_data.clear();
+        let sender = caller.address;
+        let nonce = self.nonce_of(sender)?;
+        if caller.depth >= CALL_DEPTH_LIMIT || nonce == u64::MAX || !self.can_afford(sender, inputs.value)? {
+            caller.gas.reclaim(inputs.gas_limit);
+            return Ok(None);
+        }
+        // The sender's nonce goes up even if the creation fails.
+        self.state.inc_nonce(sender)?;
+
+        let init_code_hash = keccak256(&inputs.init_code);
+        let address = match inputs.salt {
+            Some(salt) => create2_address(sender, salt, &init_code_hash),
+            None => create_address(sender, nonce),
+        };
+        self.state.access_account(address);
+        // An address that already holds code or has sent transactions cannot
+        // be deployed to; the gas handed to the creation is lost.
+        if self.state.account(address)?.is_some_and(|account| account.nonce != 0 || !account.code.is_empty()) {
+            return Ok(None);
+        }
+
+        let checkpoint = self.state.checkpoint();
+        let nonce = if self.env.cfg.spec.is_enabled_in(SpecId::SpuriousDragon) { 1 } else { 0 };
+        self.state.create_account(address, nonce)?;
+        self.state.transfer(sender, address, inputs.value)?;
+
+        let code = Bytes::from(inputs.init_code);
+        let jump_table = self.jump_table(&code, &init_code_hash);
+
+        let mut frame = Frame::new(code, jump_table, inputs.gas_limit);
+        frame.address = address;
+        frame.caller = sender;
+        frame.value = inputs.value;
+        frame.depth = caller.depth + 1;
+        frame.checkpoint = checkpoint;
+        frame.created_address = Some(address);
+        Ok(Some(frame))
+    }
+
+    /// Hands the outcome of `callee` back to the frame that called it.
+    fn return_from_call(&mut self, frame: &mut Frame, callee: Frame, reason: HaltReason) -> Result<(), StepError<DB::Error>> {
+        let (offset, size) = callee.return_range;
+        let copied = min(size, callee.output.len());
+        frame.memory.store(offset, &callee.output[..copied]);
+
+        if !matches!(reason, HaltReason::Error(_)) {
+            frame.gas.reclaim(callee.gas.remaining());
+        }
+        if reason.is_success() {
+            self.state.commit(callee.checkpoint);
+            frame.gas.record_refund(callee.gas.refunded());
+        } else {
+            self.state.revert_to(callee.checkpoint);
+        }
+        frame.return_data = callee.output;
+        Ok(frame.stack.push(U256::from(reason.is_success() as u8))?)
+    }
+
+    /// Deploys the code returned by a successful initcode frame and pushes
+    /// the new address, or zero if the creation failed.
+    fn return_from_create(&mut self, frame: &mut Frame, mut callee: Frame, reason: HaltReason, address: Address) -> Result<(), StepError<DB::Error>> {
+        let reason = match reason {
+            HaltReason::Stop | HaltReason::Return => match self.deposit_code(&mut callee, address) {
+                Ok(()) => reason,
+                Err(StepError::Evm(error)) => {
+                    callee.gas.spend_all();
+                    HaltReason::Error(error)
+                },
+                Err(error) => return Err(error),
+            },
+            reason => reason,
+        };
+
+        if !matches!(reason, HaltReason::Error(_)) {
+            frame.gas.reclaim(callee.gas.remaining());
+        }
+        if reason.is_success() {
+            self.state.commit(callee.checkpoint);
+            frame.gas.record_refund(callee.gas.refunded());
+            return Ok(frame.stack.push(address.into_word())?);
+        }
+
+        self.state.revert_to(callee.checkpoint);
+        if reason == HaltReason::Revert {
+            frame.return_data = callee.output;
+        }
+        Ok(frame.stack.push(U256::zero())?)
+    }
+
+    /// Charges for and stores the runtime code returned by initcode.
+    fn deposit_code(&mut self, callee: &mut Frame, address: Address) -> Result<(), StepError<DB::Error>> {
+        let code = std::mem::take(&mut callee.output);
+        if self.env.cfg.spec.is_enabled_in(SpecId::SpuriousDragon) && code.len() > self.env.cfg.limit_contract_code_size {
+            return Err(EvmError::CodeSizeLimit.into());
+        }
+        if self.env.cfg.spec.is_enabled_in(SpecId::London) && code.first() == Some(&0xef) {
+            return Err(EvmError::InvalidCodePrefix.into());
+        }
+        let used = callee.gas.spent();
+        match callee.gas.record_cost(gas::CODE_DEPOSIT * code.len() as u64) {
+            Ok(()) => self.state.set_code(address, Bytes::from(code)).map_err(StepError::Database)?,
+            // Frontier kept the account but left it without code, charging
+            // only what the initcode used.
+            Err(_) if !self.env.cfg.spec.is_enabled_in(SpecId::Homestead) => {
+                callee.gas.reclaim(callee.gas.limit() - used);
+            },
+            Err(error) => return Err(error.into()),
+        }
+        Ok(())
+    }
+
+    /// Whether `address` holds at least `value`, or balances are not
+    /// checked.
+    fn can_afford(&mut self, address: Address, value: U256) -> Result<bool, DB::Error> {
+        Ok(self.env.cfg.disable_balance_check || value.is_zero() || self.balance_of(address)? >= value)
+    }
+
+    /// Gas handed to a sub-call that asked for `requested`. Since Tangerine
+    /// Whistle at most all but one 64th of the remaining gas is forwarded.
+    fn call_gas(&self, remaining: u64, requested: U256) -> u64 {
+        if self.env.cfg.unlimited_gas {
+            return remaining - remaining / 64;
+        }
+        let requested = requested.min(U256::from(u64::MAX)).as_u64();
+        if self.env.cfg.spec.is_enabled_in(SpecId::TangerineWhistle) {
+            min(requested, remaining - remaining / 64)
+        } else {
+            requested
+        }
+    }
+
+    /// Executes the instruction at `frame.pc`.
+    fn step(&mut self, frame: &mut Frame) -> Result<Control, StepError<DB::Error>> {
+        if let Some(address) = frame.precompile {
+            frame.output = precompile::run(address, &frame.input, &mut frame.gas)?;
+            return Ok(Control::Halt(HaltReason::Return));
+        }
+        let Some(&opcode) = frame.code.get(frame.pc) else {
+            return Ok(Control::Halt(HaltReason::Stop));
+        };
+        let code: &[u8] = &frame.code;
+        let stack = &mut frame.stack;
+        if !self.env.cfg.spec.has_opcode(opcode) {
+            return Err(EvmError::InvalidOpcode(opcode).into());
+        }
+        frame.gas.record_cost(self.gas_schedule.static_gas(opcode))?;
+        frame.pc += 1;
+
+        match opcode {
+            STOP => {
+                return Ok(Control::Halt(HaltReason::Stop));
+            },
+            ADD => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                stack.push(a.overflowing_add(b).0)?;
+            },
+            POP => {
+                stack.pop()?;
+            },
+            MUL => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                stack.push(a.overflowing_mul(b).0)?;
+            },
+            SUB => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                stack.push(a.overflowing_sub(b).0)?;
+            },
+            DIV => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                if b.is_zero() {
+                    stack.push(U256::zero())?;
+                } else {
+                    stack.push(a / b)?;
+                }
+            },
+            SDIV => {
+                let a = I256::from(stack.pop()?);
+                let b = I256::from(stack.pop()?);
+                stack.push((a / b).into())?;
+            },
+            MOD => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                if b.is_zero() {
+                    stack.push(U256::zero())?;
+                } else {
+                    stack.push(a % b)?;
+                }
+            },
+            SMOD => {
+                let a = I256::from(stack.pop()?);
+                let b = I256::from(stack.pop()?);
+                stack.push((a % b).into())?;
+            },
+            ADDMOD => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                let n = stack.pop()?;
+                if n.is_zero() {
+                    stack.push(U256::zero())?;
+                } else {
+                    let sum = U512::from(a) + U512::from(b);
+                    stack.push(low_u256(sum % U512::from(n)))?;
+                }
+            },
+            MULMOD => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                let n = stack.pop()?;
+                if n.is_zero() {
+                    stack.push(U256::zero())?;
+                } else {
+                    stack.push(low_u256(a.full_mul(b) % U512::from(n)))?;
+                }
+            },
+            EXP => {
+                let base = stack.pop()?;
+                let exponent = stack.pop()?;
+                frame.gas.record_cost(self.gas_schedule.exp_cost(exponent))?;
+                stack.push(wrapping_pow(base, exponent))?;
+            },
+            SIGNEXTEND => {
+                let b = stack.pop()?;
+                let x = stack.pop()?;
+                stack.push(I256::sign_extend(b, x).into())?;
+            },
+            LT => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                stack.push(U256::from((a < b) as u8))?;
+            },
+            GT => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                stack.push(U256::from((a > b) as u8))?;
+            },
+            SLT => {
+                let a = I256::from(stack.pop()?);
+                let b = I256::from(stack.pop()?);
+                stack.push(U256::from((a < b) as u8))?;
+            },
+            SGT => {
+                let a = I256::from(stack.pop()?);
+                let b = I256::from(stack.pop()?);
+                stack.push(U256::from((a > b) as u8))?;
+            },
+            EQ => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                stack.push(U256::from((a == b) as u8))?;
+            },
+            ISZERO => {
+                let a = stack.pop()?;
+                stack.push(U256::from(a.is_zero() as u8))?;
+            },
+            AND => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                stack.push(a & b)?;
+            },
+            OR => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                stack.push(a | b)?;
+            },
+            XOR => {
+                let a = stack.pop()?;
+                let b = stack.pop()?;
+                stack.push(a ^ b)?;
+            },
+            NOT => {
+                let a = stack.pop()?;
+                stack.push(!a)?;
+            },
+            BYTE => {
+                let i = stack.pop()?;
+                let x = stack.pop()?;
+                // y = (x >> (248 - i * 8)) & 0xFF
+                let result = if i < U256::from(32) {
+                    (x >> (248 - i.as_usize() * 8)) & U256::from(0xff)
+                } else {
+                    U256::zero()
+                };
+                stack.push(result)?;
+            },
+            SHL => {
+                let shift = stack.pop()?;
+                let value = stack.pop()?;
+                if shift < U256::from(256) {
+                    stack.push(value << shift.as_usize())?;
+                } else {
+                    stack.push(U256::zero())?;
+                }
+            },
+            SHR => {
+                let shift = stack.pop()?;
+                let value = stack.pop()?;
+                if shift < U256::from(256) {
+                    stack.push(value >> shift.as_usize())?;
+                } else {
+                    stack.push(U256::zero())?;
+                }
+            },
+            SAR => {
+                let shift = stack.pop()?;
+                let value = I256::from(stack.pop()?);
+                stack.push(value.sar(shift).into())?;
+            },
+            MLOAD => {
+                let offset = as_offset(stack.pop()?)?;
+                expand_memory(&mut frame.gas, &mut frame.memory, offset, 32)?;
+                stack.push(frame.memory.load(offset))?;
+            },
+            MSTORE => {
+                let offset = as_offset(stack.pop()?)?;
+                let value = stack.pop()?;
+                let mut bytes = [0u8; 32];
+                value.to_big_endian(&mut bytes);
+                expand_memory(&mut frame.gas, &mut frame.memory, offset, 32)?;
+                frame.memory.store(offset, &bytes);
+            },
+            MSTORE8 => {
+                let offset = as_offset(stack.pop()?)?;
+                let value = stack.pop()?;
+                expand_memory(&mut frame.gas, &mut frame.memory, offset, 1)?;
+                frame.memory.store8(offset, value.byte(0));
+            },
+            MCOPY => {
+                let destination = stack.pop()?;
+                let source = stack.pop()?;
+                let length = stack.pop()?;
+                let (destination, length) = memory_range(destination, length)?;
+                let (source, _) = memory_range(source, U256::from(length))?;
+                expand_memory(&mut frame.gas, &mut frame.memory, max(source, destination), length)?;
+                frame.gas.record_cost(gas::copy_cost(length))?;
+                frame.memory.copy_within(source, destination, length);
+            },
+            MSIZE => {
+                stack.push(U256::from(frame.memory.size()))?;
+            },
+            JUMP => {
+                let destination = stack.pop()?;
+                frame.pc = jump_destination(&frame.jump_table, destination)?;
+            },
+            JUMPI => {
+                let destination = stack.pop()?;
+                let condition = stack.pop()?;
+                if !condition.is_zero() {
+                    frame.pc = jump_destination(&frame.jump_table, destination)?;
+                }
+            },
+            JUMPDEST => {},
+            PC => {
+                stack.push(U256::from(frame.pc - 1))?;
+            },
+            GAS => {
+                stack.push(U256::from(frame.gas.remaining()))?;
+            },
+            KECCAK256 => {
+                let offset = stack.pop()?;
+                let size = stack.pop()?;
+                let (offset, size) = memory_range(offset, size)?;
+                expand_memory(&mut frame.gas, &mut frame.memory, offset, size)?;
+                frame.gas.record_cost(gas::keccak256_cost(size))?;
+
+                let hash = keccak256(&frame.memory.slice(offset, size));
+                stack.push(hash.into())?;
+            },
+            PUSH0 => {
+                stack.push(U256::zero())?;
+            },
+            PUSH1..=PUSH32 => {
+                let size = (opcode - PUSH1 + 1) as usize;
+                let value = U256::from_big_endian(&padded_slice(code, U256::from(frame.pc), size));
+                stack.push(value)?;
+                frame.pc += size;
+            },
+            DUP1..=DUP16 => {
+                stack.dup((opcode - DUP1 + 1) as usize)?;
+            },
+            SWAP1..=SWAP16 => {
+                stack.swap((opcode - SWAP1 + 1) as usize)?;
+            },
+            ADDRESS => {
+                stack.push(frame.address.into_word())?;
+            },
+            CALLER => {
+                stack.push(frame.caller.into_word())?;
+            },
+            ORIGIN => {
+                let origin = self.env.tx.origin.unwrap_or(self.env.tx.caller);
+                stack.push(origin.into_word())?;
+            },
+            GASPRICE => {
+                stack.push(self.env.tx.gas_price)?;
+            },
+            CALLVALUE => {
+                stack.push(frame.value)?;
+            },
+            CALLDATALOAD => {
+                let index = stack.pop()?;
+                stack.push(U256::from_big_endian(&padded_slice(&frame.input, index, 32)))?;
+            },
+            CALLDATASIZE => {
+                stack.push(U256::from(frame.input.len()))?;
+            },
+            CALLDATACOPY => {
+                let memory_offset = stack.pop()?;
+                let data_offset = stack.pop()?;
+                let length = stack.pop()?;
+                copy_to_memory(&mut frame.gas, &mut frame.memory, memory_offset, &frame.input, data_offset, length)?;
+            },
+            CODESIZE => {
+                stack.push(U256::from(code.len()))?;
+            },
+            CODECOPY => {
+                let memory_offset = stack.pop()?;
+                let data_offset = stack.pop()?;
+                let length = stack.pop()?;
+                copy_to_memory(&mut frame.gas, &mut frame.memory, memory_offset, code, data_offset, length)?;
+            },
+            EXTCODESIZE => {
+                let address = Address::from_word(stack.pop()?);
+                self.access_account(&mut frame.gas, address)?;
+                stack.push(U256::from(self.code_at(address).map_err(StepError::Database)?.len()))?;
+            },
+            EXTCODECOPY => {
+                let address = Address::from_word(stack.pop()?);
+                let memory_offset = stack.pop()?;
+                let data_offset = stack.pop()?;
+                let length = stack.pop()?;
+                self.access_account(&mut frame.gas, address)?;
+                let data = self.code_at(address).map_err(StepError::Database)?;
+                copy_to_memory(&mut frame.gas, &mut frame.memory, memory_offset, &data, data_offset, length)?;
+            },
+            EXTCODEHASH => {
+                let address = Address::from_word(stack.pop()?);
+                self.access_account(&mut frame.gas, address)?;
+                // Accounts that do not exist or are empty hash to zero
+                // (EIP-1052, EIP-161).
+                let hash = match self.state.account(address).map_err(StepError::Database)? {
+                    Some(account) if !account.is_empty() => account.code_hash.into(),
+                    _ => U256::zero(),
+                };
+                stack.push(hash)?;
+            },
+            RETURNDATASIZE => {
+                stack.push(U256::from(frame.return_data.len()))?;
+            },
+            RETURNDATACOPY => {
+                let memory_offset = stack.pop()?;
+                let data_offset = stack.pop()?;
+                let length = stack.pop()?;
+                // Unlike the other copies, reading past the end of the return
+                // data is an error rather than zero padding (EIP-211).
+                let data_end = data_offset.checked_add(length).ok_or(EvmError::OutOfOffset)?;
+                if data_end > U256::from(frame.return_data.len()) {
+                    return Err(EvmError::OutOfOffset.into());
+                }
+                copy_to_memory(&mut frame.gas, &mut frame.memory, memory_offset, &frame.return_data, data_offset, length)?;
+            },
+            BALANCE => {
+                let address = Address::from_word(stack.pop()?);
+                self.access_account(&mut frame.gas, address)?;
+                stack.push(self.balance_of(address).map_err(StepError::Database)?)?;
+            },
+            SELFBALANCE => {
+                stack.push(self.balance_of(frame.address).map_err(StepError::Database)?)?;
+            },
+            COINBASE => {
+                stack.push(self.env.block.coinbase.into_word())?;
+            },
+            TIMESTAMP => {
+                stack.push(U256::from(self.env.block.timestamp))?;
+            },
+            DIFFICULTY => {
+                // PREVRANDAO since the Merge (EIP-4399). Blocks that only
+                // give a difficulty keep returning it.
+                let block = &self.env.block;
+                let difficulty = match (self.env.cfg.spec.is_enabled_in(SpecId::Merge), block.prevrandao) {
+                    (true, Some(prevrandao)) => prevrandao.into(),
+                    _ => block.difficulty,
+                };
+                stack.push(difficulty)?;
+            },
+            NUMBER => {
+                stack.push(U256::from(self.env.block.number))?;
+            },
+            CHAINID => {
+                stack.push(U256::from(self.env.cfg.chain_id))?;
+            },
+            GASLIMIT => {
+                stack.push(U256::from(self.env.block.gas_limit))?;
+            },
+            BASEFEE => {
+                stack.push(self.env.block.basefee)?;
+            },
+            BLOCKHASH => {
+                let requested = stack.pop()?;
+                let current = U256::from(self.env.block.number);
+                // Only the 256 most recent complete blocks are available.
+                let hash = if requested < current && current - requested <= U256::from(BLOCK_HASH_HISTORY) {
+                    self.state.block_hash(requested.as_u64()).map_err(StepError::Database)?.into()
+                } else {
+                    U256::zero()
+                };
+                stack.push(hash)?;
+            },
+            BLOBHASH => {
+                let index = stack.pop()?;
+                let hashes = &self.env.tx.blob_hashes;
+                let hash = match index < U256::from(hashes.len()) {
+                    true => hashes[index.as_usize()].into(),
+                    false => U256::zero(),
+                };
+                stack.push(hash)?;
+            },
+            BLOBBASEFEE => {
+                let excess_blob_gas = U256::from(self.env.block.excess_blob_gas);
+                stack.push(self.gas_schedule.blob_base_fee(excess_blob_gas))?;
+            },
+            SSTORE => {
+                if frame.is_static {
+                    return Err(EvmError::WriteProtection.into());
+                }
+                let key = stack.pop()?;
+                let value = stack.pop()?;
+                // A frame left with no more than the call stipend may not
+                // write storage (EIP-2200).
+                if self.gas_schedule.net_sstore && frame.gas.remaining() <= gas::CALL_STIPEND {
+                    return Err(EvmError::OutOfGas.into());
+                }
+                if self.state.access_slot(frame.address, key) {
+                    frame.gas.record_cost(self.gas_schedule.cold_sstore)?;
+                }
+                let original = self.state.original_value(frame.address, key).map_err(StepError::Database)?;
+                let current = self.state.sload(frame.address, key).map_err(StepError::Database)?;
+                frame.gas.record_cost(self.gas_schedule.sstore_cost(original, current, value))?;
+                frame.gas.record_refund(self.gas_schedule.sstore_refund(original, current, value));
+                self.state.sstore(frame.address, key, value).map_err(StepError::Database)?;
+            },
+            SLOAD => {
+                let key = stack.pop()?;
+                if self.state.access_slot(frame.address, key) {
+                    frame.gas.record_cost(self.gas_schedule.cold_sload)?;
+                }
+                stack.push(self.state.sload(frame.address, key).map_err(StepError::Database)?)?;
+            },
+            TLOAD => {
+                let key = stack.pop()?;
+                stack.push(self.state.tload(frame.address, key))?;
+            },
+            TSTORE => {
+                if frame.is_static {
+                    return Err(EvmError::WriteProtection.into());
+                }
+                let key = stack.pop()?;
+                let value = stack.pop()?;
+                self.state.tstore(frame.address, key, value);
+            },
+            CALL | CALLCODE | DELEGATECALL | STATICCALL => {
+                let gas_limit = stack.pop()?;
+                let to = Address::from_word(stack.pop()?);
+                let value = if matches!(opcode, CALL | CALLCODE) { stack.pop()? } else { U256::zero() };
+                let args_offset = stack.pop()?;
+                let args_size = stack.pop()?;
+                let ret_offset = stack.pop()?;
+                let ret_size = stack.pop()?;
+                if opcode == CALL && frame.is_static && !value.is_zero() {
+                    return Err(EvmError::WriteProtection.into());
+                }
+                self.access_account(&mut frame.gas, to)?;
+
+                let (args_offset, args_size) = memory_range(args_offset, args_size)?;
+                let (ret_offset, ret_size) = memory_range(ret_offset, ret_size)?;
+                expand_memory(&mut frame.gas, &mut frame.memory, args_offset, args_size)?;
+                expand_memory(&mut frame.gas, &mut frame.memory, ret_offset, ret_size)?;
+
+                if !value.is_zero() {
+                    frame.gas.record_cost(gas::CALL_VALUE)?;
+                }
+                if opcode == CALL && self.is_new_account(to, value).map_err(StepError::Database)? {
+                    frame.gas.record_cost(gas::NEW_ACCOUNT)?;
+                }
+                let call_gas = self.call_gas(frame.gas.remaining(), gas_limit);
+                frame.gas.record_cost(call_gas)?;
+                let stipend = if value.is_zero() { 0 } else { gas::CALL_STIPEND };
+
+                let (address, caller, value) = match opcode {
+                    CALL => (to, frame.address, value),
+                    CALLCODE => (frame.address, frame.address, value),
+                    DELEGATECALL => (frame.address, frame.caller, frame.value),
+                    _ => (to, frame.address, U256::zero()),
+                };
+                return Ok(Control::Call(Box::new(CallInputs {
+                    code_address: to,
+                    address,
+                    caller,
+                    value,
+                    input: frame.memory.slice(args_offset, args_size),
+                    gas_limit: call_gas + stipend,
+                    is_static: frame.is_static || opcode == STATICCALL,
+                    return_range: (ret_offset, ret_size),
+                    transfers_value: matches!(opcode, CALL | CALLCODE),
+                })));
+            },
+            CREATE | CREATE2 => {
+                if frame.is_static {
+                    return Err(EvmError::WriteProtection.into());
                 }
+                let value = stack.pop()?;
+                let offset = stack.pop()?;
+                let size = stack.pop()?;
+                let salt = if opcode == CREATE2 { Some(stack.pop()?) } else { None };
 
-                for v in &stack {
-                    println!("stack value {:#X},", v);
+                let (offset, size) = memory_range(offset, size)?;
+                if self.env.cfg.spec.is_enabled_in(SpecId::Shanghai) && size > self.env.cfg.limit_initcode_size() {
+                    return Err(EvmError::InitCodeSizeLimit.into());
+                }
+                expand_memory(&mut frame.gas, &mut frame.memory, offset, size)?;
+                frame.gas.record_cost(self.gas_schedule.initcode_word * gas::words(size))?;
+                if salt.is_some() {
+                    frame.gas.record_cost(gas::keccak256_cost(size))?;
+                }
+
+                let remaining = frame.gas.remaining();
+                let gas_limit = if self.env.cfg.spec.is_enabled_in(SpecId::TangerineWhistle) {
+                    remaining - remaining / 64
+                } else {
+                    remaining
                 };
+                frame.gas.record_cost(gas_limit)?;
+
+                return Ok(Control::Create(Box::new(CreateInputs {
+                    value,
+                    init_code: frame.memory.slice(offset, size),
+                    salt,
+                    gas_limit,
+                })));
+            },
+            SELFDESTRUCT => {
+                if frame.is_static {
+                    return Err(EvmError::WriteProtection.into());
+                }
+                let beneficiary = Address::from_word(stack.pop()?);
+                // The static cost holds no warm read to build on, so a cold
+                // beneficiary pays the whole cold access cost (EIP-2929).
+                if self.state.access_account(beneficiary) && self.env.cfg.spec.is_enabled_in(SpecId::Berlin) {
+                    frame.gas.record_cost(gas::COLD_ACCOUNT_ACCESS)?;
+                }
+                let balance = self.balance_of(frame.address).map_err(StepError::Database)?;
+                if self.env.cfg.spec.is_enabled_in(SpecId::TangerineWhistle) && self.is_new_account(beneficiary, balance).map_err(StepError::Database)? {
+                    frame.gas.record_cost(gas::NEW_ACCOUNT)?;
+                }
+
+                // Since Cancun only contracts created in the same transaction
+                // are destroyed; others just send their balance (EIP-6780).
+                let destroy = !self.env.cfg.spec.is_enabled_in(SpecId::Cancun) || self.state.is_created(frame.address);
+                let was_destroyed = self.state.selfdestruct(frame.address, beneficiary, destroy).map_err(StepError::Database)?;
+                if destroy && !was_destroyed {
+                    frame.gas.record_refund(self.gas_schedule.selfdestruct_refund as i64);
+                }
+                return Ok(Control::Halt(HaltReason::Stop));
+            },
+            LOG0..=LOG4 => {
+                if frame.is_static {
+                    return Err(EvmError::WriteProtection.into());
+                }
+                let offset = stack.pop()?;
+                let size = stack.pop()?;
+                let mut topics = Vec::with_capacity((opcode - LOG0) as usize);
+                for _ in LOG0..opcode {
+                    topics.push(stack.pop()?);
+                }
+                let (offset, size) = memory_range(offset, size)?;
+                expand_memory(&mut frame.gas, &mut frame.memory, offset, size)?;
+                frame.gas.record_cost(gas::log_data_cost(size))?;
+
+                self.state.log(Log {
+                    address: frame.address,
+                    topics,
+                    data: frame.memory.slice(offset, size),
+                });
+            },
+            RETURN | REVERT => {
+                let offset = stack.pop()?;
+                let size = stack.pop()?;
+                let (offset, size) = memory_range(offset, size)?;
+                expand_memory(&mut frame.gas, &mut frame.memory, offset, size)?;
+
+                frame.output = frame.memory.slice(offset, size);
+                return Ok(Control::Halt(if opcode == RETURN { HaltReason::Return } else { HaltReason::Revert }));
+            },
+            _ => {
+                return Err(EvmError::InvalidOpcode(opcode).into());
+            }
+        }
+
+        Ok(Control::Continue)
+    }
 
-                pc += 1;
-            //     continue
-            // }
+    fn jump_table(&mut self, code: &[u8], code_hash: &B256) -> Arc<JumpTable> {
+        if self.jump_tables.len() >= JUMP_TABLE_CACHE_SIZE && !self.jump_tables.contains_key(code_hash) {
+            self.jump_tables.clear();
+        }
+        self.jump_tables
+            .entry(*code_hash)
+            .or_insert_with(|| Arc::new(JumpTable::analyze(code)))
+            .clone()
+    }
 
+    /// Marks `address` as accessed, charging the cold surcharge on its first
+    /// access in the transaction.
+    fn access_account(&mut self, gas: &mut Gas, address: Address) -> Result<(), EvmError> {
+        if self.state.access_account(address) {
+            gas.record_cost(self.gas_schedule.cold_account_access)?;
         }
-        
-        return stack.into_iter().rev().collect();
+        Ok(())
+    }
+
+    fn balance_of(&mut self, address: Address) -> Result<U256, DB::Error> {
+        Ok(self.state.account(address)?.map(|account| account.balance).unwrap_or_default())
     }
 
-    fn toAddress(&self, address_value: U256) -> String {
-        let mut bytes = [0u8; 32];
-        address_value.to_big_endian(&mut bytes);
-        
-        let hex_str = hex::encode(bytes);
-        
-        let address = &hex_str[hex_str.len() - 40..];
-        let eth_address = format!("0x{}", address);
-        eth_address
+    fn code_at(&mut self, address: Address) -> Result<Bytes, DB::Error> {
+        Ok(self.state.account(address)?.map(|account| account.code.clone()).unwrap_or_default())
+    }
+
+    fn nonce_of(&mut self, address: Address) -> Result<u64, DB::Error> {
+        Ok(self.state.account(address)?.map(|account| account.nonce).unwrap_or_default())
+    }
+
+    /// Whether a CALL or SELFDESTRUCT to `address` has to pay for bringing
+    /// a new account into existence.
+    fn is_new_account(&mut self, address: Address, value: U256) -> Result<bool, DB::Error> {
+        let spurious_dragon = self.env.cfg.spec.is_enabled_in(SpecId::SpuriousDragon);
+        let account = self.state.account(address)?;
+        Ok(if spurious_dragon {
+            !value.is_zero() && account.is_none_or(Account::is_empty)
+        } else {
+            account.is_none()
+        })
     }
-    
 }
 
 // revm
-pub struct OpCode(u8);
+pub struct OpCode(pub u8);
 
 pub const STOP: u8 = 0x00;
 pub const ADD: u8 = 0x01;
@@ -844,7 +1676,10 @@ pub const REVERT: u8 = 0xfd;
 pub const INVALID: u8 = 0xfe;
 pub const ADDRESS: u8 = 0x30;
 pub const BALANCE: u8 = 0x31;
+pub const PREVRANDAO: u8 = 0x44;
 pub const BASEFEE: u8 = 0x48;
+pub const BLOBHASH: u8 = 0x49;
+pub const BLOBBASEFEE: u8 = 0x4a;
 pub const ORIGIN: u8 = 0x32;
 pub const CALLER: u8 = 0x33;
 pub const CALLVALUE: u8 = 0x34;
@@ -1,4 +1,4 @@
-use evm::{RustEVM, Tx, StateInfo, Block};
+use evm::{Address, BlockEnv, CfgEnv, Env, HexBytes, InMemoryDB, Quantity, RustEVM, Tx, TxEnv, StateInfo, Block};
 use primitive_types::U256;
 use serde::Deserialize;
 
@@ -20,32 +20,42 @@ struct Code {
 
 #[derive(Debug, Deserialize)]
 struct Expect {
-    stack: Option<Vec<String>>,
+    stack: Option<Vec<Quantity<U256>>>,
     success: Option<bool>,
     #[serde(rename = "return")]
     ret: Option<String>,
+    logs: Option<Vec<ExpectLog>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExpectLog {
+    address: Address,
+    data: HexBytes,
+    topics: Vec<Quantity<U256>>,
 }
 
 fn main() {
     let text = std::fs::read_to_string("../evm.json").unwrap();
-    let data: Vec<Evmtest> = serde_json::from_str(&text).unwrap();
+    let data = load_fixtures(&text).unwrap_or_else(|err| panic!("{}", err));
 
     let total = data.len();
-    let evm = RustEVM::new();
+    // The fixtures are written without gas or balances in mind.
+    let cfg = CfgEnv::builder()
+        .unlimited_gas(true)
+        .disable_balance_check(true)
+        .build();
 
     for (index, test) in data.iter().enumerate() {
         println!("Test {} of {}: {}", index + 1, total, test.name);
 
         let code: Vec<u8> = hex::decode(&test.code.bin).unwrap();
 
-        let actual_stack = evm.clone().evaluate(&code, &test.tx, &test.state, &test.block);
+        let db = test.state.as_ref().map(|state| InMemoryDB::from(state.to_state())).unwrap_or_default();
+        // An in-memory database cannot fail.
+        let Ok(result) = RustEVM::with_db(db).evaluate(&code, fixture_env(cfg, test));
+        let actual_stack = &result.stack;
 
-        let mut expected_stack: Vec<U256> = Vec::new();
-        if let Some(ref stacks) = test.expect.stack {
-            for stack in stacks {
-                expected_stack.push(stack.parse().unwrap());
-            }
-        }
+        let expected_stack: Vec<U256> = test.expect.stack.iter().flatten().map(|value| value.0).collect();
 
         let mut matching = actual_stack.len() == expected_stack.len();
         if matching {
@@ -57,6 +67,32 @@ fn main() {
             }
         }
 
+        if let Some(success) = test.expect.success {
+            if result.is_success() != success {
+                matching = false;
+            }
+        }
+
+        if let Some(ref ret) = test.expect.ret {
+            if hex::encode(&result.return_data) != ret.to_lowercase() {
+                matching = false;
+            }
+        }
+
+        if let Some(ref logs) = test.expect.logs {
+            if logs.len() != result.logs.len() {
+                matching = false;
+            }
+            for (expected, actual) in logs.iter().zip(result.logs.iter()) {
+                let topics: Vec<U256> = expected.topics.iter().map(|topic| topic.0).collect();
+                if expected.address != actual.address
+                    || *expected.data.0 != *actual.data
+                    || topics != actual.topics {
+                    matching = false;
+                }
+            }
+        }
+
         if !matching {
             println!("Instructions: \n{}\n", test.code.asm);
 
@@ -65,17 +101,54 @@ fn main() {
                 println!("  {:#X},", v);
             }
             println!("]\n");
+            if let Some(success) = test.expect.success {
+                println!("Expected success: {}", success);
+            }
+            if let Some(ref ret) = test.expect.ret {
+                println!("Expected return: {}", ret);
+            }
+            if let Some(ref logs) = test.expect.logs {
+                println!("Expected logs: {:?}", logs);
+            }
 
             println!("Got: [");
             for v in actual_stack {
                 println!("  {:#X},", v);
             }
             println!("]\n");
+            println!("Got halt reason: {:?}", result.halt_reason);
+            println!("Got return: {}", hex::encode(&result.return_data));
+            println!("Got logs: {:?}\n", result.logs);
 
             println!("Progress: {}/{}\n\n", index, total);
-            panic!("Stack mismatch");
+            panic!("Test failed");
         }
         println!("PASS");
     }
     println!("Congratulations!");
 }
+
+/// The environment `test` runs in. Its chain id is given with the block.
+fn fixture_env(mut cfg: CfgEnv, test: &Evmtest) -> Env {
+    if let Some(chain_id) = test.block.as_ref().and_then(|block| block.chainid) {
+        cfg.chain_id = chain_id;
+    }
+    let block = test.block.as_ref().map(BlockEnv::from).unwrap_or_default();
+    let tx = test.tx.as_ref().map(TxEnv::from).unwrap_or_default();
+    Env::new(cfg, block, tx)
+}
+
+/// Reads the fixtures one at a time so that an error names the fixture it
+/// comes from.
+fn load_fixtures(text: &str) -> Result<Vec<Evmtest>, String> {
+    let values: Vec<serde_json::Value> = serde_json::from_str(text).map_err(|err| err.to_string())?;
+    values.into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let name = value.get("name")
+                .and_then(|name| name.as_str())
+                .map_or_else(|| format!("#{}", index + 1), str::to_string);
+            serde_json::from_value(value).map_err(|err| format!("fixture {:?}: {}", name, err))
+        })
+        .collect()
+}
@@ -0,0 +1,341 @@
+use std::{fmt, str::FromStr, sync::Arc};
+use primitive_types::U256;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::keccak256;
+
+/// Immutable byte string that is cheap to clone, used for contract code.
+pub type Bytes = Arc<[u8]>;
+
+/// Error returned when a hex string is not a valid `Address` or `B256`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseHexError {
+    /// The string does not hold the expected number of hex digits.
+    InvalidLength { expected: usize, found: usize },
+    InvalidCharacter(char),
+    /// A mixed-case address whose case does not match its EIP-55 checksum.
+    InvalidChecksum,
+    /// The value does not fit into the type.
+    Overflow,
+}
+
+impl fmt::Display for ParseHexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseHexError::InvalidLength { expected, found } => {
+                write!(f, "expected {} hex digits, found {}", expected, found)
+            },
+            ParseHexError::InvalidCharacter(c) => write!(f, "invalid hex character {:?}", c),
+            ParseHexError::InvalidChecksum => write!(f, "invalid EIP-55 checksum"),
+            ParseHexError::Overflow => write!(f, "value does not fit"),
+        }
+    }
+}
+
+impl std::error::Error for ParseHexError {}
+
+/// Decodes exactly `N` bytes of hex, with or without a `0x` prefix.
+fn parse_fixed<const N: usize>(value: &str) -> Result<[u8; N], ParseHexError> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(ParseHexError::InvalidCharacter(c));
+    }
+    if digits.len() != 2 * N {
+        return Err(ParseHexError::InvalidLength { expected: 2 * N, found: digits.len() });
+    }
+    let mut bytes = [0u8; N];
+    hex::decode_to_slice(digits, &mut bytes).map_err(|_| ParseHexError::InvalidLength { expected: 2 * N, found: digits.len() })?;
+    Ok(bytes)
+}
+
+// B256
+
+/// A 32-byte hash.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct B256(pub [u8; 32]);
+
+impl B256 {
+    pub const ZERO: B256 = B256([0; 32]);
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for B256 {
+    fn from(bytes: [u8; 32]) -> Self {
+        B256(bytes)
+    }
+}
+
+impl From<U256> for B256 {
+    fn from(value: U256) -> Self {
+        let mut bytes = [0u8; 32];
+        value.to_big_endian(&mut bytes);
+        B256(bytes)
+    }
+}
+
+impl From<B256> for U256 {
+    fn from(value: B256) -> Self {
+        U256::from_big_endian(&value.0)
+    }
+}
+
+impl AsRef<[u8]> for B256 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl FromStr for B256 {
+    type Err = ParseHexError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse_fixed(value).map(B256)
+    }
+}
+
+impl fmt::Debug for B256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl fmt::Display for B256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+/// Accepts 64 hex digits or, as fixtures write them, any hex quantity that
+/// fits into 32 bytes.
+impl<'de> Deserialize<'de> for B256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        match value.strip_prefix("0x").unwrap_or(&value).len() {
+            64 => value.parse(),
+            _ => parse_quantity(&value).map(B256::from),
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+// Address
+
+/// A 20-byte account address.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Address(pub [u8; 20]);
+
+impl Address {
+    pub const ZERO: Address = Address([0; 20]);
+
+    /// The address held in the low 20 bytes of a stack word. The upper 12
+    /// bytes are ignored, as the EVM does.
+    pub fn from_word(word: U256) -> Self {
+        let bytes = B256::from(word).0;
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&bytes[12..]);
+        Address(address)
+    }
+
+    /// The address as a stack word, zero-padded on the left.
+    pub fn into_word(self) -> U256 {
+        U256::from(self)
+    }
+
+    /// The address in EIP-55 mixed-case checksum form.
+    pub fn to_checksum(&self) -> String {
+        let lower = hex::encode(self.0);
+        let hash = keccak256(lower.as_bytes());
+        let digits: String = lower.char_indices()
+            .map(|(i, c)| {
+                let nibble = (hash.0[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+                if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+            })
+            .collect();
+        format!("0x{}", digits)
+    }
+}
+
+impl From<[u8; 20]> for Address {
+    fn from(bytes: [u8; 20]) -> Self {
+        Address(bytes)
+    }
+}
+
+impl From<Address> for U256 {
+    fn from(address: Address) -> Self {
+        U256::from_big_endian(&address.0)
+    }
+}
+
+/// Fails if `value` has bits set above the low 160.
+impl TryFrom<U256> for Address {
+    type Error = ParseHexError;
+
+    fn try_from(value: U256) -> Result<Self, Self::Error> {
+        if value.bits() > 160 {
+            return Err(ParseHexError::Overflow);
+        }
+        Ok(Address::from_word(value))
+    }
+}
+
+/// Parses 40 hex digits. Mixed-case input must carry a valid EIP-55
+/// checksum.
+impl FromStr for Address {
+    type Err = ParseHexError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let address = Address(parse_fixed(value)?);
+        let digits = value.strip_prefix("0x").unwrap_or(value);
+        let mixed_case = digits.chars().any(|c| c.is_ascii_lowercase()) && digits.chars().any(|c| c.is_ascii_uppercase());
+        if mixed_case && address.to_checksum()[2..] != *digits {
+            return Err(ParseHexError::InvalidChecksum);
+        }
+        Ok(address)
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum())
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum())
+    }
+}
+
+/// Accepts 40 hex digits or, as fixtures write them, a shortened hex
+/// quantity such as `0x1000`.
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        match value.strip_prefix("0x").unwrap_or(&value).len() {
+            40 => value.parse(),
+            _ => parse_quantity(&value).and_then(Address::try_from),
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+/// Parses a hex quantity of at most 64 digits.
+fn parse_quantity(value: &str) -> Result<U256, ParseHexError> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(ParseHexError::InvalidCharacter(c));
+    }
+    if digits.is_empty() {
+        return Err(ParseHexError::InvalidLength { expected: 1, found: 0 });
+    }
+    U256::from_str_radix(digits, 16).map_err(|_| ParseHexError::Overflow)
+}
+
+// Fixture values
+
+/// A number as fixtures write it: a JSON number, a `0x`-prefixed hex string
+/// or a decimal string. `T` is `U256` or a narrower integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quantity<T>(pub T);
+
+impl<'de, T: TryFrom<U256>> Deserialize<'de> for Quantity<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct QuantityVisitor;
+
+        impl<'de> de::Visitor<'de> for QuantityVisitor {
+            type Value = U256;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a number, a 0x-prefixed hex string or a decimal string")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<U256, E> {
+                Ok(U256::from(value))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<U256, E> {
+                let parsed = match value.strip_prefix("0x") {
+                    Some(_) => parse_quantity(value).map_err(|err| err.to_string()),
+                    None => U256::from_dec_str(value).map_err(|_| match value.chars().find(|c| !c.is_ascii_digit()) {
+                        Some(c) => format!("invalid decimal character {:?}", c),
+                        None => "value does not fit".to_string(),
+                    }),
+                };
+                parsed.map_err(|err| E::custom(format_args!("invalid quantity {:?}: {}", value, err)))
+            }
+        }
+
+        let value = deserializer.deserialize_any(QuantityVisitor)?;
+        T::try_from(value)
+            .map(Quantity)
+            .map_err(|_| de::Error::custom(format_args!("quantity {} is too large", value)))
+    }
+}
+
+/// Hex-encoded bytes, with or without a `0x` prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HexBytes(pub Bytes);
+
+impl<'de> Deserialize<'de> for HexBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        hex::decode(value.strip_prefix("0x").unwrap_or(&value))
+            .map(|bytes| HexBytes(Bytes::from(bytes)))
+            .map_err(|err| de::Error::custom(format_args!("invalid hex bytes: {}", err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Examples from EIP-55, in checksum form.
+    const CHECKSUMMED: [&str; 8] = [
+        // All caps
+        "0x52908400098527886E0F7030069857D2E4169EE7",
+        "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+        // All lower
+        "0xde709f2102306220921060314715629080e2fb77",
+        "0x27b1fdb04752bbc536007a920d24acb045561c26",
+        // Normal
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn to_checksum_follows_eip_55() {
+        for checksummed in CHECKSUMMED {
+            let address: Address = checksummed.to_lowercase().parse().unwrap();
+            assert_eq!(address.to_checksum(), checksummed);
+        }
+    }
+
+    #[test]
+    fn checksummed_addresses_parse() {
+        for checksummed in CHECKSUMMED {
+            let address: Address = checksummed.parse().unwrap();
+            assert_eq!(address.to_string(), checksummed);
+        }
+    }
+
+    #[test]
+    fn a_wrong_case_letter_fails_the_checksum() {
+        // 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed with its first `A` in
+        // lower case.
+        let parsed = "0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse::<Address>();
+        assert_eq!(parsed, Err(ParseHexError::InvalidChecksum));
+    }
+
+    #[test]
+    fn single_case_addresses_skip_the_checksum() {
+        let upper: Address = "0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED".parse().unwrap();
+        let lower: Address = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed".parse().unwrap();
+        assert_eq!(upper, lower);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::*;
+
+/// Ethereum hardforks, in activation order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum SpecId {
+    Frontier,
+    Homestead,
+    TangerineWhistle,
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Merge,
+    Shanghai,
+    Cancun,
+    #[default]
+    Prague,
+}
+
+impl SpecId {
+    pub const LATEST: SpecId = SpecId::Prague;
+
+    /// Whether the rules introduced in `fork` apply under this spec.
+    pub fn is_enabled_in(self, fork: SpecId) -> bool {
+        self >= fork
+    }
+
+    /// Whether `opcode` exists under this spec. Bytes that are not opcodes
+    /// in any fork are reported as enabled and rejected by the interpreter.
+    pub fn has_opcode(self, opcode: u8) -> bool {
+        let introduced_in = match opcode {
+            DELEGATECALL => SpecId::Homestead,
+            REVERT | RETURNDATASIZE | RETURNDATACOPY | STATICCALL => SpecId::Byzantium,
+            SHL | SHR | SAR | EXTCODEHASH | CREATE2 => SpecId::Constantinople,
+            CHAINID | SELFBALANCE => SpecId::Istanbul,
+            BASEFEE => SpecId::London,
+            PUSH0 => SpecId::Shanghai,
+            TLOAD | TSTORE | MCOPY | BLOBHASH | BLOBBASEFEE => SpecId::Cancun,
+            _ => SpecId::Frontier,
+        };
+        self.is_enabled_in(introduced_in)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opcodes_arrive_with_their_fork() {
+        let cases = [
+            (DELEGATECALL, SpecId::Frontier, SpecId::Homestead),
+            (REVERT, SpecId::SpuriousDragon, SpecId::Byzantium),
+            (RETURNDATACOPY, SpecId::SpuriousDragon, SpecId::Byzantium),
+            (STATICCALL, SpecId::SpuriousDragon, SpecId::Byzantium),
+            (SAR, SpecId::Byzantium, SpecId::Constantinople),
+            (CREATE2, SpecId::Byzantium, SpecId::Constantinople),
+            (CHAINID, SpecId::Petersburg, SpecId::Istanbul),
+            (BASEFEE, SpecId::Berlin, SpecId::London),
+            (PUSH0, SpecId::Merge, SpecId::Shanghai),
+            (TLOAD, SpecId::Shanghai, SpecId::Cancun),
+            (MCOPY, SpecId::Shanghai, SpecId::Cancun),
+            (BLOBHASH, SpecId::Shanghai, SpecId::Cancun),
+        ];
+        for (opcode, before, introduced_in) in cases {
+            assert!(!before.has_opcode(opcode), "{:#04x} under {:?}", opcode, before);
+            assert!(introduced_in.has_opcode(opcode), "{:#04x} under {:?}", opcode, introduced_in);
+            assert!(SpecId::LATEST.has_opcode(opcode), "{:#04x} under {:?}", opcode, SpecId::LATEST);
+        }
+    }
+
+    #[test]
+    fn frontier_opcodes_are_always_there() {
+        for opcode in [STOP, ADD, KECCAK256, CALL, SELFDESTRUCT] {
+            assert!(SpecId::Frontier.has_opcode(opcode));
+        }
+    }
+}
@@ -0,0 +1,425 @@
+use primitive_types::U256;
+
+use crate::*;
+
+/// Runs the hex-encoded `code` under `spec` with a gas limit of `gas_limit`.
+fn execute(spec: SpecId, gas_limit: u64, code: &str) -> ExecutionResult {
+    execute_with_db(InMemoryDB::new(), spec, gas_limit, code)
+}
+
+fn execute_with_db(db: InMemoryDB, spec: SpecId, gas_limit: u64, code: &str) -> ExecutionResult {
+    let code = hex::decode(code).unwrap();
+    let env = Env::new(CfgEnv::new(spec), BlockEnv::default(), TxEnv::builder().gas_limit(gas_limit).build());
+    RustEVM::with_db(db).evaluate(&code, env).unwrap()
+}
+
+/// Gas used by `code` on top of the intrinsic gas of the transaction.
+fn execution_gas(spec: SpecId, code: &str) -> u64 {
+    let result = execute(spec, 1_000_000, code);
+    assert!(result.is_success(), "{:?}", result.halt_reason);
+    result.gas_used - 21_000
+}
+
+fn address(value: u64) -> Address {
+    Address::from_word(U256::from(value))
+}
+
+// Gas
+
+#[test]
+fn static_costs_follow_the_fork() {
+    for (spec, balance, sload) in [
+        (SpecId::Frontier, 20, 50),
+        (SpecId::TangerineWhistle, 400, 200),
+        (SpecId::Istanbul, 700, 800),
+        // The executing account is warm, storage slots start cold.
+        (SpecId::Berlin, 100, 2_100),
+    ] {
+        // ADDRESS BALANCE
+        assert_eq!(execution_gas(spec, "3031"), 2 + balance, "{:?}", spec);
+        // PUSH1 0 SLOAD
+        assert_eq!(execution_gas(spec, "600054"), 3 + sload, "{:?}", spec);
+    }
+}
+
+#[test]
+fn memory_expansion_is_quadratic() {
+    // PUSH1 1 PUSH1 0 MSTORE: one word.
+    assert_eq!(execution_gas(SpecId::Cancun, "6001600052"), 9 + 3);
+    // PUSH1 1 PUSH2 0x03e0 MSTORE: 32 words.
+    assert_eq!(execution_gas(SpecId::Cancun, "60016103e052"), 9 + 3 * 32 + 32 * 32 / 512);
+    // PUSH1 1 PUSH2 0xffe0 MSTORE: 2048 words.
+    assert_eq!(execution_gas(SpecId::Cancun, "600161ffe052"), 9 + 3 * 2048 + 2048 * 2048 / 512);
+    // Writing to memory that is already paid for costs nothing extra.
+    assert_eq!(execution_gas(SpecId::Cancun, "60016103e05260016103e052"), 18 + 3 * 32 + 32 * 32 / 512);
+}
+
+#[test]
+fn exp_charges_per_exponent_byte() {
+    // PUSH2 0x0100 PUSH1 2 EXP: a two-byte exponent.
+    assert_eq!(execution_gas(SpecId::Homestead, "61010060020a"), 6 + 10 + 2 * 10);
+    assert_eq!(execution_gas(SpecId::SpuriousDragon, "61010060020a"), 6 + 10 + 2 * 50);
+    // PUSH1 0 PUSH1 2 EXP
+    assert_eq!(execution_gas(SpecId::Cancun, "600060020a"), 6 + 10);
+}
+
+#[test]
+fn keccak256_charges_per_word() {
+    // PUSH1 33 PUSH1 0 KECCAK256: two words hashed and two words of memory.
+    assert_eq!(execution_gas(SpecId::Cancun, "6021600020"), 6 + 30 + 2 * 6 + 2 * 3);
+}
+
+#[test]
+fn log_charges_per_topic_and_byte() {
+    // PUSH1 2 PUSH1 1 PUSH1 4 PUSH1 0 LOG2: two topics, four bytes.
+    assert_eq!(execution_gas(SpecId::Cancun, "6002600160046000a2"), 12 + 375 + 2 * 375 + 4 * 8 + 3);
+}
+
+#[test]
+fn storage_access_is_cold_then_warm() {
+    // PUSH1 0 SLOAD PUSH1 0 SLOAD
+    assert_eq!(execution_gas(SpecId::Berlin, "60005460005450"), 6 + 2_100 + 100 + 2);
+}
+
+/// Slot 0 of the executing account holds 1.
+fn db_with_slot() -> InMemoryDB {
+    let mut account = Account::new();
+    account.sstore(U256::zero(), U256::one());
+    let mut db = InMemoryDB::new();
+    db.insert_account(Address::ZERO, account);
+    db
+}
+
+#[test]
+fn sstore_set_and_reset_eip_2200() {
+    // PUSH1 1 PUSH1 0 SSTORE PUSH1 0 PUSH1 0 SSTORE: dirty slot restored.
+    let result = execute(SpecId::Istanbul, 100_000, "60016000556000600055");
+    assert_eq!(result.gas_refunded, 20_000 - 800);
+    assert_eq!(result.gas_used, 21_000 + 12 + 20_000 + 800 - result.gas_refunded);
+
+    // PUSH1 0 PUSH1 0 SSTORE: clearing pays 15000 back, capped at half.
+    let result = execute_with_db(db_with_slot(), SpecId::Istanbul, 100_000, "6000600055");
+    let spent = 21_000 + 6 + 5_000;
+    assert_eq!(result.gas_refunded, spent / 2);
+    assert_eq!(result.gas_used, spent - spent / 2);
+}
+
+#[test]
+fn sstore_refunds_eip_3529() {
+    // PUSH1 1 PUSH1 0 SSTORE PUSH1 0 PUSH1 0 SSTORE: the refund of 19900
+    // is capped at a fifth of everything spent.
+    let result = execute(SpecId::London, 100_000, "60016000556000600055");
+    let spent = 21_000 + 12 + 2_100 + 20_000 + 100;
+    assert_eq!(result.gas_refunded, spent / 5);
+    assert_eq!(result.gas_used, spent - spent / 5);
+
+    // PUSH1 0 PUSH1 0 SSTORE: clearing a cold slot pays 4800 back.
+    let result = execute_with_db(db_with_slot(), SpecId::London, 100_000, "6000600055");
+    assert_eq!(result.gas_refunded, 4_800);
+    assert_eq!(result.gas_used, 21_000 + 6 + 2_100 + 2_900 - 4_800);
+}
+
+#[test]
+fn sstore_needs_more_than_the_stipend() {
+    // PUSH1 1 PUSH1 0 SSTORE with 4 gas left.
+    let result = execute(SpecId::Istanbul, 21_010, "6001600055");
+    assert_eq!(result.halt_reason, HaltReason::Error(EvmError::OutOfGas));
+    assert_eq!(result.gas_used, 21_010);
+}
+
+#[test]
+fn out_of_gas_consumes_the_gas_limit() {
+    // Not enough for the intrinsic gas.
+    let result = execute(SpecId::Cancun, 20_000, "00");
+    assert_eq!(result.halt_reason, HaltReason::Error(EvmError::OutOfGas));
+    assert_eq!(result.gas_used, 20_000);
+
+    // PUSH1 1 PUSH2 0xffe0 MSTORE cannot pay for its memory.
+    let result = execute(SpecId::Cancun, 25_000, "600161ffe052");
+    assert_eq!(result.halt_reason, HaltReason::Error(EvmError::OutOfGas));
+    assert_eq!(result.gas_used, 25_000);
+}
+
+// Code deposit
+
+/// Stores initcode that returns 0x1000 zero bytes, too much code to pay
+/// for, and runs it with CREATE.
+const CREATE_UNAFFORDABLE_CODE: &str = concat!(
+    "656110006000f3", // PUSH6 initcode: PUSH2 0x1000 PUSH1 0 RETURN
+    "600052",         // PUSH1 0 MSTORE
+    "6006601a6000f0", // CREATE(0, 26, 6)
+);
+
+#[test]
+fn frontier_keeps_initcode_gas_when_deposit_fails() {
+    let result = execute(SpecId::Frontier, 100_000, CREATE_UNAFFORDABLE_CODE);
+    assert!(result.is_success());
+    assert_ne!(result.stack[0], U256::zero());
+    // Caller: intrinsic 21000, pushes and MSTORE 21, memory 3, CREATE 32000.
+    // Initcode: pushes 6, memory for 0x1000 bytes 416.
+    assert_eq!(result.gas_used, 53_021 + 422);
+}
+
+#[test]
+fn homestead_fails_creation_when_deposit_fails() {
+    let result = execute(SpecId::Homestead, 100_000, CREATE_UNAFFORDABLE_CODE);
+    assert!(result.is_success());
+    assert_eq!(result.stack[0], U256::zero());
+    assert_eq!(result.gas_used, 100_000);
+}
+
+// Account access
+
+#[test]
+fn selfdestruct_to_cold_beneficiary_pays_full_cold_cost() {
+    // PUSH2 0xdead SELFDESTRUCT
+    let result = execute(SpecId::Prague, 100_000, "61deadff");
+    assert!(result.is_success());
+    assert_eq!(result.gas_used, 21_000 + 3 + 5_000 + 2_600);
+}
+
+#[test]
+fn extcodehash_of_missing_account_is_zero() {
+    // PUSH2 0xdead EXTCODEHASH
+    let result = execute(SpecId::Cancun, 100_000, "61dead3f");
+    assert!(result.is_success());
+    assert_eq!(result.stack, vec![U256::zero()]);
+    assert_eq!(result.gas_used, 21_000 + 3 + 2_600);
+}
+
+#[test]
+fn extcodehash_of_existing_accounts() {
+    let mut db = InMemoryDB::new();
+    db.insert_account(address(0xdead), Account::new());
+    let mut funded = Account::new();
+    funded.balance = U256::one();
+    db.insert_account(address(0xbeef), funded);
+    let mut contract = Account::new();
+    contract.set_code(Bytes::from(vec![0x00]));
+    db.insert_account(address(0xc0de), contract);
+
+    // EXTCODEHASH of 0xdead, 0xbeef and 0xc0de
+    let result = execute_with_db(db, SpecId::Cancun, 100_000, "61dead3f61beef3f61c0de3f");
+    assert_eq!(result.stack, vec![keccak256(&[0x00]).into(), KECCAK_EMPTY.into(), U256::zero()]);
+}
+
+/// Fails every storage read, and otherwise reads from `InMemoryDB`.
+struct FailingStorageDB(InMemoryDB);
+
+impl Database for FailingStorageDB {
+    type Error = &'static str;
+
+    fn basic(&mut self, address: Address) -> Result<Option<BasicAccount>, Self::Error> {
+        Ok(self.0.basic(address).unwrap())
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytes, Self::Error> {
+        Ok(self.0.code_by_hash(code_hash).unwrap())
+    }
+
+    fn storage(&mut self, _address: Address, _index: U256) -> Result<U256, Self::Error> {
+        Err("storage unavailable")
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        Ok(self.0.block_hash(number).unwrap())
+    }
+}
+
+#[test]
+fn database_errors_abort_execution() {
+    // PUSH1 0 SLOAD
+    let code = hex::decode("600054").unwrap();
+    let result = RustEVM::with_db(FailingStorageDB(InMemoryDB::new())).evaluate(&code, Env::default());
+    assert_eq!(result.unwrap_err(), "storage unavailable");
+}
+
+#[test]
+fn database_errors_in_sub_calls_abort_execution() {
+    let mut db = InMemoryDB::new();
+    let mut callee = Account::new();
+    // PUSH1 0 SLOAD
+    callee.set_code(Bytes::from(hex::decode("600054").unwrap()));
+    db.insert_account(address(0xc0de), callee);
+
+    // CALL(GAS, 0xc0de, 0, 0, 0, 0, 0)
+    let code = hex::decode("6000600060006000600061c0de5af1").unwrap();
+    let result = RustEVM::with_db(FailingStorageDB(db)).evaluate(&code, Env::default());
+    assert_eq!(result.unwrap_err(), "storage unavailable");
+}
+
+#[test]
+fn transactions_run_on_the_state_left_by_earlier_ones() {
+    let mut evm = RustEVM::new();
+    let env = Env::new(CfgEnv::new(SpecId::Cancun), BlockEnv::default(), TxEnv::default());
+
+    // PUSH1 1 PUSH1 0 SSTORE
+    let stored = evm.evaluate(&hex::decode("6001600055").unwrap(), env.clone()).unwrap();
+    assert!(stored.is_success());
+    assert_eq!(evm.db_mut().sload(Address::ZERO, U256::zero()), Ok(U256::one()));
+
+    // PUSH1 0 SLOAD: the slot is cold again in a new transaction.
+    let loaded = evm.evaluate(&hex::decode("600054").unwrap(), env).unwrap();
+    assert_eq!(loaded.stack, vec![U256::one()]);
+    assert_eq!(loaded.gas_used, 21_000 + 3 + 2_100);
+}
+
+#[test]
+fn transaction_value_moves_from_the_caller() {
+    let mut caller = Account::new();
+    caller.balance = U256::from(100);
+    let mut db = InMemoryDB::new();
+    db.insert_account(address(0xca11), caller);
+    let tx = TxEnv::builder().caller(address(0xca11)).to(address(0x1000)).value(U256::from(10)).build();
+    let env = Env::new(CfgEnv::new(SpecId::Cancun), BlockEnv::default(), tx);
+
+    // PUSH2 0xca11 BALANCE SELFBALANCE
+    let result = RustEVM::with_db(db).evaluate(&hex::decode("61ca113147").unwrap(), env).unwrap();
+    assert!(result.is_success());
+    assert_eq!(result.stack, vec![U256::from(10), U256::from(90)]);
+}
+
+#[test]
+fn transaction_value_beyond_the_balance_fails() {
+    let mut evm = RustEVM::new();
+    let tx = TxEnv::builder().caller(address(0xca11)).to(address(0x1000)).value(U256::from(10)).build();
+    let env = Env::new(CfgEnv::new(SpecId::Cancun), BlockEnv::default(), tx);
+
+    let result = evm.evaluate(&[], env).unwrap();
+    assert_eq!(result.halt_reason, HaltReason::Error(EvmError::InsufficientBalance));
+    assert!(evm.db_mut().account(address(0x1000)).unwrap().is_none());
+}
+
+#[test]
+fn destroyed_accounts_lose_their_storage_in_later_transactions() {
+    let mut evm = RustEVM::with_db(db_with_slot());
+    let env = Env::new(CfgEnv::new(SpecId::London), BlockEnv::default(), TxEnv::default());
+
+    // PUSH2 0xdead SELFDESTRUCT
+    let destroyed = evm.evaluate(&hex::decode("61deadff").unwrap(), env.clone()).unwrap();
+    assert!(destroyed.is_success());
+    assert_eq!(evm.db_mut().sload(Address::ZERO, U256::zero()), Ok(U256::zero()));
+
+    // PUSH1 0 SLOAD
+    let loaded = evm.evaluate(&hex::decode("600054").unwrap(), env).unwrap();
+    assert_eq!(loaded.stack, vec![U256::zero()]);
+}
+
+#[test]
+fn identity_precompile_returns_its_input() {
+    // MSTORE(0, 0x42), CALL(GAS, 0x04, 0, 31, 1, 0, 1), MLOAD(0)
+    let result = execute(SpecId::Cancun, 100_000, "6042600052600160006001601f600060045af1600051");
+    assert!(result.is_success());
+    let expected = (U256::from(0x42) << 248) | U256::from(0x42);
+    assert_eq!(result.stack, vec![expected, U256::one()]);
+}
+
+#[test]
+fn unsupported_precompiles_fail_the_call() {
+    // CALL(1000, 0x02, 0, 0, 0, 0, 0)
+    let result = execute(SpecId::Cancun, 100_000, "6000600060006000600060026103e8f1");
+    assert!(result.is_success());
+    assert_eq!(result.stack, vec![U256::zero()]);
+    assert_eq!(result.gas_used, 21_000 + 7 * 3 + 100 + 1_000);
+}
+
+#[test]
+fn precompiles_follow_the_fork() {
+    // CALL(1000, 0x05, 0, 0, 0, 0, 0): MODEXP arrived in Byzantium.
+    let code = "6000600060006000600060056103e8f1";
+    assert_eq!(execute(SpecId::Homestead, 100_000, code).stack, vec![U256::one()]);
+    assert_eq!(execute(SpecId::Byzantium, 100_000, code).stack, vec![U256::zero()]);
+}
+
+#[test]
+fn jump_table_cache_is_bounded() {
+    let mut evm = RustEVM::new();
+    for index in 0..JUMP_TABLE_CACHE_SIZE as u16 + 10 {
+        // PUSH2 index: every transaction runs different code.
+        let code = [0x61, (index >> 8) as u8, index as u8];
+        evm.evaluate(&code, Env::default()).unwrap();
+        assert!(evm.jump_tables.len() <= JUMP_TABLE_CACHE_SIZE);
+    }
+}
+
+fn parse_address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+#[test]
+fn create_address_from_sender_and_nonce() {
+    let sender = parse_address("0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+    let expected = [
+        (0, "0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"),
+        (1, "0x343c43a37d37dff08ae8c4a11544c718abb4fcf8"),
+        (2, "0xf778b86fa74e846c4f0a1fbd1335fe81c00a0c91"),
+        (3, "0xfffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c"),
+        (0x7f, "0x06d9a77f5e4b311bae8d559db9cdb4df94104aa0"),
+        // From 0x80 the nonce is RLP-encoded as a string of bytes.
+        (0x80, "0x08e190dcb7b73f5fcdabb43e102215c83659a76d"),
+        (0x100, "0x3837c1ae70354f670550c746580199ac6a73cb0a"),
+        (0xffff, "0x65260eecff4edebabe134f76f1f39a91defde56c"),
+    ];
+    for (nonce, address) in expected {
+        assert_eq!(create_address(sender, nonce), parse_address(address), "nonce {}", nonce);
+    }
+}
+
+#[test]
+fn create2_address_eip_1014_examples() {
+    let examples = [
+        ("0x0000000000000000000000000000000000000000", "0000000000000000000000000000000000000000000000000000000000000000", "00", "0x4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38"),
+        ("0xdeadbeef00000000000000000000000000000000", "0000000000000000000000000000000000000000000000000000000000000000", "00", "0xb928f69bb1d91cd65274e3c79d8986362984fda3"),
+        ("0xdeadbeef00000000000000000000000000000000", "000000000000000000000000feed000000000000000000000000000000000000", "00", "0xd04116cdd17bebe565eb2422f2497e06cc1c9833"),
+        ("0x0000000000000000000000000000000000000000", "0000000000000000000000000000000000000000000000000000000000000000", "deadbeef", "0x70f2b2914a2a4b783faefb75f459a580616fcb5e"),
+        ("0x00000000000000000000000000000000deadbeef", "00000000000000000000000000000000000000000000000000000000cafebabe", "deadbeef", "0x60f3f640a8508fc6a86d45df051962668e1e8ac7"),
+        ("0x0000000000000000000000000000000000000000", "0000000000000000000000000000000000000000000000000000000000000000", "", "0xe33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0"),
+    ];
+    for (sender, salt, init_code, address) in examples {
+        let salt = U256::from_big_endian(&hex::decode(salt).unwrap());
+        let init_code_hash = keccak256(&hex::decode(init_code).unwrap());
+        assert_eq!(create2_address(parse_address(sender), salt, &init_code_hash), parse_address(address));
+    }
+}
+
+#[test]
+fn reverted_sub_calls_undo_their_transient_storage() {
+    let mut db = InMemoryDB::new();
+    let mut callee = Account::new();
+    // TSTORE(0, 2) REVERT(0, 0)
+    callee.set_code(Bytes::from(hex::decode("600260005d60006000fd").unwrap()));
+    db.insert_account(address(0xc0de), callee);
+
+    // TSTORE(0, 1), DELEGATECALL(GAS, 0xc0de, 0, 0, 0, 0), TLOAD(0)
+    let result = execute_with_db(db, SpecId::Cancun, 100_000, "600160005d600060006000600061c0de5af460005c");
+    assert!(result.is_success());
+    assert_eq!(result.stack, vec![U256::one(), U256::zero()]);
+}
+
+#[test]
+fn transient_storage_is_empty_in_the_next_transaction() {
+    let mut evm = RustEVM::new();
+    let env = Env::new(CfgEnv::new(SpecId::Cancun), BlockEnv::default(), TxEnv::default());
+
+    // TSTORE(0, 1) TLOAD(0)
+    let stored = evm.evaluate(&hex::decode("600160005d60005c").unwrap(), env.clone()).unwrap();
+    assert_eq!(stored.stack, vec![U256::one()]);
+
+    // TLOAD(0)
+    let loaded = evm.evaluate(&hex::decode("60005c").unwrap(), env).unwrap();
+    assert_eq!(loaded.stack, vec![U256::zero()]);
+}
+
+#[test]
+fn opcodes_from_later_forks_are_invalid() {
+    // PUSH0
+    let result = execute(SpecId::London, 100_000, "5f");
+    assert_eq!(result.halt_reason, HaltReason::Error(EvmError::InvalidOpcode(0x5f)));
+    assert_eq!(result.gas_used, 100_000);
+    assert_eq!(execute(SpecId::Shanghai, 100_000, "5f").stack, vec![U256::zero()]);
+
+    // PUSH1 0 TLOAD
+    let result = execute(SpecId::Shanghai, 100_000, "60005c");
+    assert_eq!(result.halt_reason, HaltReason::Error(EvmError::InvalidOpcode(0x5c)));
+    assert!(execute(SpecId::Cancun, 100_000, "60005c").is_success());
+}
@@ -58,6 +58,7 @@ const OPCODES = {
   MSIZE: 0x59,
   GAS: 0x5a,
   JUMPDEST: 0x5b,
+  MCOPY: 0x5e,
   PUSH1: 0x60,
   PUSH2: 0x61,
   PUSH3: 0x62,